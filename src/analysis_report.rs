@@ -0,0 +1,161 @@
+use crate::piece::PieceColor;
+use crate::search_info::Score;
+use std::fmt::{Display, Formatter};
+
+const GOOD_CENTIPAWN_LOSS: i32 = 50;
+const INACCURACY_CENTIPAWN_LOSS: i32 = 100;
+const MISTAKE_CENTIPAWN_LOSS: i32 = 300;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum MoveClassification {
+    Best,
+    Good,
+    Inaccuracy,
+    Mistake,
+    Blunder,
+}
+
+pub struct MoveAnalysis {
+    pub move_number: u32,
+    pub color: PieceColor,
+    pub san: String,
+    pub best_move_san: String,
+    pub evaluation: Score,
+    pub centipawn_loss: i32,
+    pub accuracy: f64,
+    pub classification: MoveClassification,
+}
+
+pub struct AnalysisReport {
+    pub white_name: String,
+    pub black_name: String,
+    pub result: String,
+    pub moves: Vec<MoveAnalysis>,
+}
+
+impl MoveClassification {
+    pub fn from_centipawn_loss(centipawn_loss: i32, is_best_move: bool) -> Self {
+        match centipawn_loss {
+            _ if is_best_move => MoveClassification::Best,
+            centipawn_loss if centipawn_loss <= 0 => MoveClassification::Best,
+            centipawn_loss if centipawn_loss < GOOD_CENTIPAWN_LOSS => MoveClassification::Good,
+            centipawn_loss if centipawn_loss < INACCURACY_CENTIPAWN_LOSS => {
+                MoveClassification::Inaccuracy
+            }
+            centipawn_loss if centipawn_loss < MISTAKE_CENTIPAWN_LOSS => {
+                MoveClassification::Mistake
+            }
+            _ => MoveClassification::Blunder,
+        }
+    }
+}
+
+impl AnalysisReport {
+    pub fn accuracy(&self, color: PieceColor) -> Option<f64> {
+        let accuracies: Vec<f64> = self
+            .moves_by(color)
+            .map(|move_analysis| move_analysis.accuracy)
+            .collect();
+        match accuracies.len() {
+            0 => None,
+            move_count => Some(accuracies.iter().sum::<f64>() / move_count as f64),
+        }
+    }
+
+    pub fn average_centipawn_loss(&self, color: PieceColor) -> Option<i32> {
+        let losses: Vec<i32> = self
+            .moves_by(color)
+            .map(|move_analysis| move_analysis.centipawn_loss)
+            .collect();
+        match losses.len() {
+            0 => None,
+            move_count => Some(losses.iter().sum::<i32>() / move_count as i32),
+        }
+    }
+
+    pub fn count(&self, color: PieceColor, classification: MoveClassification) -> usize {
+        self.moves_by(color)
+            .filter(|move_analysis| move_analysis.classification == classification)
+            .count()
+    }
+
+    fn moves_by(&self, color: PieceColor) -> impl Iterator<Item = &MoveAnalysis> {
+        self.moves
+            .iter()
+            .filter(move |move_analysis| move_analysis.color == color)
+    }
+
+    fn fmt_summary(&self, f: &mut Formatter<'_>, color: PieceColor) -> std::fmt::Result {
+        let name = match color {
+            PieceColor::White => &self.white_name,
+            PieceColor::Black => &self.black_name,
+        };
+        let Some(accuracy) = self.accuracy(color) else {
+            return writeln!(f, "{name}: no moves played");
+        };
+
+        writeln!(
+            f,
+            "{name}: accuracy {accuracy:.1}%, average centipawn loss {}, {} inaccuracies, {} mistakes, {} blunders",
+            self.average_centipawn_loss(color).unwrap_or_default(),
+            self.count(color, MoveClassification::Inaccuracy),
+            self.count(color, MoveClassification::Mistake),
+            self.count(color, MoveClassification::Blunder)
+        )
+    }
+}
+
+impl Display for MoveClassification {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let display_str = match self {
+            MoveClassification::Best => "best",
+            MoveClassification::Good => "good",
+            MoveClassification::Inaccuracy => "inaccuracy",
+            MoveClassification::Mistake => "mistake",
+            MoveClassification::Blunder => "blunder",
+        };
+        write!(f, "{}", display_str)
+    }
+}
+
+impl Display for MoveAnalysis {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        // 12. Nf3      +0.35  best
+        // 12... Qxb2   -2.10  blunder (-310, best was Qd7)
+        let move_label = match self.color {
+            PieceColor::White => format!("{}. {}", self.move_number, self.san),
+            PieceColor::Black => format!("{}... {}", self.move_number, self.san),
+        };
+        write!(
+            f,
+            "{move_label:<16} {:>7}  {}",
+            self.evaluation.to_string(),
+            self.classification
+        )?;
+
+        if self.classification != MoveClassification::Best {
+            write!(
+                f,
+                " (-{}, best was {})",
+                self.centipawn_loss, self.best_move_san
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for AnalysisReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "ANALYSIS: {} vs {} ({})",
+            self.white_name, self.black_name, self.result
+        )?;
+        for move_analysis in &self.moves {
+            writeln!(f, "{move_analysis}")?;
+        }
+
+        self.fmt_summary(f, PieceColor::White)?;
+        self.fmt_summary(f, PieceColor::Black)
+    }
+}
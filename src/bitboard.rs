@@ -0,0 +1,158 @@
+use crate::piece::PieceColor;
+
+// Squares are numbered rank * 8 + file, so a1 is 0, h1 is 7 and h8 is 63
+pub const BOARD_SIZE: usize = 8;
+pub const SQUARE_COUNT: usize = BOARD_SIZE * BOARD_SIZE;
+pub const DARK_SQUARES: u64 = 0xAA55_AA55_AA55_AA55;
+
+const KNIGHT_OFFSETS: [(i32, i32); 8] = [
+    (1, 2),
+    (2, 1),
+    (2, -1),
+    (1, -2),
+    (-1, -2),
+    (-2, -1),
+    (-2, 1),
+    (-1, 2),
+];
+const KING_OFFSETS: [(i32, i32); 8] = [
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+];
+const DIAGONAL_DIRECTIONS: [(i32, i32); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
+const STRAIGHT_DIRECTIONS: [(i32, i32); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+
+static KNIGHT_ATTACKS: [u64; SQUARE_COUNT] = get_step_attacks(&KNIGHT_OFFSETS);
+static KING_ATTACKS: [u64; SQUARE_COUNT] = get_step_attacks(&KING_OFFSETS);
+static WHITE_PAWN_ATTACKS: [u64; SQUARE_COUNT] = get_step_attacks(&[(1, -1), (1, 1)]);
+static BLACK_PAWN_ATTACKS: [u64; SQUARE_COUNT] = get_step_attacks(&[(-1, -1), (-1, 1)]);
+static DIAGONAL_RAYS: [[u64; SQUARE_COUNT]; 4] = get_rays(&DIAGONAL_DIRECTIONS);
+static STRAIGHT_RAYS: [[u64; SQUARE_COUNT]; 4] = get_rays(&STRAIGHT_DIRECTIONS);
+
+pub const fn square_index(rank: usize, file: usize) -> usize {
+    rank * BOARD_SIZE + file
+}
+
+pub const fn square_bit(square: usize) -> u64 {
+    1 << square
+}
+
+pub fn squares(mut bitboard: u64) -> impl Iterator<Item = usize> {
+    // Pops the lowest set bit each step
+    std::iter::from_fn(move || match bitboard {
+        0 => None,
+        _ => {
+            let square = bitboard.trailing_zeros() as usize;
+            bitboard &= bitboard - 1;
+            Some(square)
+        }
+    })
+}
+
+pub fn knight_attacks(square: usize) -> u64 {
+    KNIGHT_ATTACKS[square]
+}
+
+pub fn king_attacks(square: usize) -> u64 {
+    KING_ATTACKS[square]
+}
+
+pub fn pawn_attacks(color: PieceColor, square: usize) -> u64 {
+    match color {
+        PieceColor::White => WHITE_PAWN_ATTACKS[square],
+        PieceColor::Black => BLACK_PAWN_ATTACKS[square],
+    }
+}
+
+pub fn bishop_attacks(square: usize, occupancy: u64) -> u64 {
+    get_sliding_attacks(&DIAGONAL_RAYS, &DIAGONAL_DIRECTIONS, square, occupancy)
+}
+
+pub fn rook_attacks(square: usize, occupancy: u64) -> u64 {
+    get_sliding_attacks(&STRAIGHT_RAYS, &STRAIGHT_DIRECTIONS, square, occupancy)
+}
+
+fn get_sliding_attacks(
+    rays: &[[u64; SQUARE_COUNT]; 4],
+    directions: &[(i32, i32); 4],
+    square: usize,
+    occupancy: u64,
+) -> u64 {
+    let mut attacks = 0;
+
+    // Each ray stops at the first blocker, found at the low end of the ray when it runs up the board
+    for (direction_rays, (rank_offset, file_offset)) in rays.iter().zip(directions) {
+        let ray = direction_rays[square];
+        let blockers = ray & occupancy;
+        if blockers == 0 {
+            attacks |= ray;
+            continue;
+        }
+
+        let blocker = match rank_offset * BOARD_SIZE as i32 + file_offset > 0 {
+            true => blockers.trailing_zeros() as usize,
+            false => 63 - blockers.leading_zeros() as usize,
+        };
+        attacks |= ray ^ direction_rays[blocker];
+    }
+
+    attacks
+}
+
+const fn get_step_attacks(offsets: &[(i32, i32)]) -> [u64; SQUARE_COUNT] {
+    let mut attacks = [0; SQUARE_COUNT];
+
+    let mut square = 0;
+    while square < SQUARE_COUNT {
+        let mut offset_index = 0;
+        while offset_index < offsets.len() {
+            attacks[square] |= get_offset_bit(square, offsets[offset_index]);
+            offset_index += 1;
+        }
+        square += 1;
+    }
+
+    attacks
+}
+
+const fn get_rays(directions: &[(i32, i32); 4]) -> [[u64; SQUARE_COUNT]; 4] {
+    let mut rays = [[0; SQUARE_COUNT]; 4];
+
+    let mut direction_index = 0;
+    while direction_index < directions.len() {
+        let (rank_offset, file_offset) = directions[direction_index];
+        let mut square = 0;
+        while square < SQUARE_COUNT {
+            let mut distance = 1;
+            while distance < BOARD_SIZE as i32 {
+                let bit = get_offset_bit(square, (rank_offset * distance, file_offset * distance));
+                if bit == 0 {
+                    break;
+                }
+                rays[direction_index][square] |= bit;
+                distance += 1;
+            }
+            square += 1;
+        }
+        direction_index += 1;
+    }
+
+    rays
+}
+
+const fn get_offset_bit(square: usize, (rank_offset, file_offset): (i32, i32)) -> u64 {
+    // Empty when the offset runs off the edge of the board
+    let rank = (square / BOARD_SIZE) as i32 + rank_offset;
+    let file = (square % BOARD_SIZE) as i32 + file_offset;
+
+    match rank >= 0 && rank < BOARD_SIZE as i32 && file >= 0 && file < BOARD_SIZE as i32 {
+        true => square_bit(square_index(rank as usize, file as usize)),
+        false => 0,
+    }
+}
@@ -0,0 +1,114 @@
+use crate::chess_move::ChessMove;
+use crate::game_board::GameBoard;
+use crate::piece::{Piece, PieceColor, PieceType};
+use crate::square::Square;
+use std::error::Error;
+use std::io::IsTerminal;
+
+const BOARD_SIZE: usize = 8;
+const LIGHT_SQUARE_COLOR: &str = "\x1b[48;5;180m";
+const DARK_SQUARE_COLOR: &str = "\x1b[48;5;137m";
+const LIGHT_LAST_MOVE_COLOR: &str = "\x1b[48;5;186m";
+const DARK_LAST_MOVE_COLOR: &str = "\x1b[48;5;143m";
+const CHECK_COLOR: &str = "\x1b[48;5;167m";
+const WHITE_PIECE_COLOR: &str = "\x1b[1;38;5;231m";
+const BLACK_PIECE_COLOR: &str = "\x1b[1;38;5;16m";
+const RESET_COLOR: &str = "\x1b[0m";
+
+pub struct BoardRenderer {
+    is_flipped: bool,
+    use_color: bool,
+}
+
+impl BoardRenderer {
+    pub fn from(is_flipped: bool) -> Self {
+        // Escape codes only make sense on a real terminal, not in a log file or a pipe
+        Self {
+            is_flipped,
+            use_color: std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none(),
+        }
+    }
+
+    pub fn render(
+        &self,
+        board: &GameBoard,
+        last_move: Option<&ChessMove>,
+    ) -> Result<String, Box<dyn Error>> {
+        let last_move_squares = match last_move {
+            Some(chess_move) => vec![
+                (
+                    chess_move.start.rank_index()?,
+                    chess_move.start.file_index()?,
+                ),
+                (chess_move.end.rank_index()?, chess_move.end.file_index()?),
+            ],
+            None => Vec::new(),
+        };
+        let checked_king = Piece::from(board.side_to_move(), PieceType::King);
+        let is_in_check = board.is_in_check(board.side_to_move());
+
+        // White sits at the bottom unless the board is flipped to black's point of view
+        let rank_order: Vec<usize> = match self.is_flipped {
+            true => (0..BOARD_SIZE).collect(),
+            false => (0..BOARD_SIZE).rev().collect(),
+        };
+        let file_order: Vec<usize> = match self.is_flipped {
+            true => (0..BOARD_SIZE).rev().collect(),
+            false => (0..BOARD_SIZE).collect(),
+        };
+
+        let mut output = String::new();
+        for &rank_index in &rank_order {
+            output.push_str(format!(" {} ", Square::index_to_rank(rank_index)?).as_str());
+
+            for &file_index in &file_order {
+                let square = Square::from(
+                    Square::index_to_rank(rank_index)?,
+                    Square::index_to_file(file_index)?,
+                    None,
+                );
+                let piece = board.piece_at(&square)?;
+
+                if !self.use_color {
+                    let glyph = piece.map_or('·', |piece| piece.unicode_char());
+                    output.push_str(format!(" {glyph} ").as_str());
+                    continue;
+                }
+
+                let is_light_square = (rank_index + file_index) % 2 == 1;
+                let is_last_move = last_move_squares.contains(&(rank_index, file_index));
+                let background_color = match (is_light_square, is_last_move) {
+                    _ if is_in_check && piece == Some(&checked_king) => CHECK_COLOR,
+                    (true, true) => LIGHT_LAST_MOVE_COLOR,
+                    (false, true) => DARK_LAST_MOVE_COLOR,
+                    (true, false) => LIGHT_SQUARE_COLOR,
+                    (false, false) => DARK_SQUARE_COLOR,
+                };
+
+                // Solid glyphs read better on colored squares, the foreground tells the sides apart
+                let (piece_color, glyph) = match piece {
+                    Some(piece) => (
+                        match piece.color {
+                            PieceColor::White => WHITE_PIECE_COLOR,
+                            PieceColor::Black => BLACK_PIECE_COLOR,
+                        },
+                        Piece::from(PieceColor::Black, piece.piece_type).unicode_char(),
+                    ),
+                    None => ("", ' '),
+                };
+                output.push_str(
+                    format!("{background_color}{piece_color} {glyph} {RESET_COLOR}").as_str(),
+                );
+            }
+            output.push('\n');
+        }
+
+        output.push_str("   ");
+        for &file_index in &file_order {
+            output.push_str(format!(" {} ", Square::index_to_file(file_index)?).as_str());
+        }
+        output.push('\n');
+
+        Ok(output)
+    }
+}
@@ -0,0 +1,54 @@
+use crate::bitboard::{self, BOARD_SIZE, SQUARE_COUNT};
+use crate::piece::Piece;
+use crate::square::Square;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+// Square numbered rank * 8 + file like the bitboards, a1 is 0 and h8 is 63
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BoardSquare(u8);
+
+impl BoardSquare {
+    pub const fn from_index(index: usize) -> Self {
+        // Indexes come from bitboards and board loops so they are always on the board
+        debug_assert!(index < SQUARE_COUNT);
+        Self(index as u8)
+    }
+
+    pub fn from_square(square: &Square) -> Result<Self, Box<dyn Error>> {
+        Ok(Self::from_index(bitboard::square_index(
+            square.rank_index()?,
+            square.file_index()?,
+        )))
+    }
+
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+
+    pub fn rank_index(self) -> usize {
+        self.index() / BOARD_SIZE
+    }
+
+    pub fn file_index(self) -> usize {
+        self.index() % BOARD_SIZE
+    }
+
+    pub fn to_square(self, piece: Option<Piece>) -> Square {
+        Square::from_indexes(self.rank_index(), self.file_index(), piece)
+    }
+
+    pub fn uci_notation(self) -> String {
+        format!(
+            "{}{}",
+            (b'a' + self.file_index() as u8) as char,
+            (b'1' + self.rank_index() as u8) as char
+        )
+    }
+}
+
+impl Display for BoardSquare {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.uci_notation())
+    }
+}
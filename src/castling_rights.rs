@@ -0,0 +1,166 @@
+use crate::piece::PieceColor;
+use std::error::Error;
+
+const KING_SIDE_ROOK_FILE: usize = 7;
+const QUEEN_SIDE_ROOK_FILE: usize = 0;
+const FILE_CHARS: [char; 8] = ['a', 'b', 'c', 'd', 'e', 'f', 'g', 'h'];
+
+// Each right remembers the file of its rook, Chess960 rooks do not start in the corners
+#[derive(Clone, Copy, PartialEq, Hash)]
+pub struct CastlingRights {
+    pub white_king_side: Option<usize>,
+    pub white_queen_side: Option<usize>,
+    pub black_king_side: Option<usize>,
+    pub black_queen_side: Option<usize>,
+}
+
+impl CastlingRights {
+    pub fn all() -> Self {
+        Self {
+            white_king_side: Some(KING_SIDE_ROOK_FILE),
+            white_queen_side: Some(QUEEN_SIDE_ROOK_FILE),
+            black_king_side: Some(KING_SIDE_ROOK_FILE),
+            black_queen_side: Some(QUEEN_SIDE_ROOK_FILE),
+        }
+    }
+
+    pub fn none() -> Self {
+        Self {
+            white_king_side: None,
+            white_queen_side: None,
+            black_king_side: None,
+            black_queen_side: None,
+        }
+    }
+
+    pub fn from_fen_notation(
+        notation: &str,
+        king_files: [Option<usize>; 2],
+        rook_files: [Vec<usize>; 2],
+    ) -> Result<Self, Box<dyn Error>> {
+        // KQkq, X-FEN or Shredder-FEN (HAha), king and rook files come from the color's home rank
+        let mut castling_rights = Self::none();
+
+        if notation == "-" {
+            return Ok(castling_rights);
+        }
+
+        for c in notation.chars() {
+            let color = match c.is_ascii_uppercase() {
+                true => PieceColor::White,
+                false => PieceColor::Black,
+            };
+            let king_file = king_files[color as usize].ok_or_else(|| {
+                format!("Found castling right '{c}' without a king on the home rank: {notation}")
+            })?;
+            let color_rook_files = &rook_files[color as usize];
+
+            // K and Q mean the outermost rook on that side of the king
+            let rook_file = match c.to_ascii_lowercase() {
+                'k' => color_rook_files
+                    .iter()
+                    .filter(|file| **file > king_file)
+                    .max()
+                    .copied(),
+                'q' => color_rook_files
+                    .iter()
+                    .filter(|file| **file < king_file)
+                    .min()
+                    .copied(),
+                file_char => FILE_CHARS
+                    .iter()
+                    .position(|item| *item == file_char)
+                    .filter(|file| color_rook_files.contains(file)),
+            }
+            .ok_or_else(|| {
+                format!("Found castling right '{c}' without a matching rook: {notation}")
+            })?;
+
+            match (color, rook_file > king_file) {
+                (PieceColor::White, true) => castling_rights.white_king_side = Some(rook_file),
+                (PieceColor::White, false) => castling_rights.white_queen_side = Some(rook_file),
+                (PieceColor::Black, true) => castling_rights.black_king_side = Some(rook_file),
+                (PieceColor::Black, false) => castling_rights.black_queen_side = Some(rook_file),
+            }
+        }
+
+        Ok(castling_rights)
+    }
+
+    pub fn fen_notation(&self, is_chess960: bool) -> String {
+        // Chess960 positions use Shredder-FEN rook files, which never leave a right ambiguous
+        let notation: String = [
+            (self.white_king_side, 'K'),
+            (self.white_queen_side, 'Q'),
+            (self.black_king_side, 'k'),
+            (self.black_queen_side, 'q'),
+        ]
+        .iter()
+        .filter_map(|(rook_file, c)| {
+            let rook_file = (*rook_file)?;
+            match (is_chess960, c.is_ascii_uppercase()) {
+                (false, _) => Some(*c),
+                (true, true) => Some(FILE_CHARS[rook_file].to_ascii_uppercase()),
+                (true, false) => Some(FILE_CHARS[rook_file]),
+            }
+        })
+        .collect();
+
+        match notation.is_empty() {
+            true => String::from("-"),
+            false => notation,
+        }
+    }
+
+    pub fn king_side(&self, color: PieceColor) -> Option<usize> {
+        match color {
+            PieceColor::White => self.white_king_side,
+            PieceColor::Black => self.black_king_side,
+        }
+    }
+
+    pub fn queen_side(&self, color: PieceColor) -> Option<usize> {
+        match color {
+            PieceColor::White => self.white_queen_side,
+            PieceColor::Black => self.black_queen_side,
+        }
+    }
+
+    pub fn is_standard(&self) -> bool {
+        // Rights that only ever use the corner rooks can be written as plain KQkq
+        [self.white_king_side, self.black_king_side]
+            .iter()
+            .all(|rook_file| rook_file.is_none_or(|file| file == KING_SIDE_ROOK_FILE))
+            && [self.white_queen_side, self.black_queen_side]
+                .iter()
+                .all(|rook_file| rook_file.is_none_or(|file| file == QUEEN_SIDE_ROOK_FILE))
+    }
+
+    pub fn remove_king_side(&mut self, color: PieceColor) {
+        match color {
+            PieceColor::White => self.white_king_side = None,
+            PieceColor::Black => self.black_king_side = None,
+        }
+    }
+
+    pub fn remove_queen_side(&mut self, color: PieceColor) {
+        match color {
+            PieceColor::White => self.white_queen_side = None,
+            PieceColor::Black => self.black_queen_side = None,
+        }
+    }
+
+    pub fn remove_for_rook_file(&mut self, color: PieceColor, file: usize) {
+        if self.king_side(color) == Some(file) {
+            self.remove_king_side(color);
+        }
+        if self.queen_side(color) == Some(file) {
+            self.remove_queen_side(color);
+        }
+    }
+
+    pub fn remove_all(&mut self, color: PieceColor) {
+        self.remove_king_side(color);
+        self.remove_queen_side(color);
+    }
+}
@@ -0,0 +1,84 @@
+use crate::bitboard::BOARD_SIZE;
+use crate::piece::{Piece, PieceColor, PieceType};
+use std::error::Error;
+
+pub const STARTING_POSITION_COUNT: usize = 960;
+
+// Every way to put two knights on the five squares left after the bishops and queen are placed
+const KNIGHT_PLACEMENTS: [(usize, usize); 10] = [
+    (0, 1),
+    (0, 2),
+    (0, 3),
+    (0, 4),
+    (1, 2),
+    (1, 3),
+    (1, 4),
+    (2, 3),
+    (2, 4),
+    (3, 4),
+];
+const FILE_CHARS: [char; BOARD_SIZE] = ['a', 'b', 'c', 'd', 'e', 'f', 'g', 'h'];
+
+pub fn get_starting_fen(index: usize) -> Result<String, Box<dyn Error>> {
+    let back_rank = get_back_rank(index)?;
+    let white_rank: String = back_rank
+        .iter()
+        .map(|piece_type| Piece::from(PieceColor::White, *piece_type).fen_char())
+        .collect();
+
+    // Shredder-FEN castling names the rook files, the rooks sit either side of the king
+    let mut rook_files = back_rank
+        .iter()
+        .enumerate()
+        .filter(|(_, piece_type)| **piece_type == PieceType::Rook)
+        .map(|(file, _)| FILE_CHARS[file]);
+    let queen_side_file = rook_files.next().unwrap_or_default();
+    let king_side_file = rook_files.next().unwrap_or_default();
+
+    Ok(format!(
+        "{}/pppppppp/8/8/8/8/PPPPPPPP/{white_rank} w {}{}{king_side_file}{queen_side_file} - 0 1",
+        white_rank.to_ascii_lowercase(),
+        king_side_file.to_ascii_uppercase(),
+        queen_side_file.to_ascii_uppercase(),
+    ))
+}
+
+fn get_back_rank(index: usize) -> Result<[PieceType; BOARD_SIZE], Box<dyn Error>> {
+    // Scharnagl numbering, each digit of the index in a mixed base places the next piece
+    if index >= STARTING_POSITION_COUNT {
+        return Err(Box::from(format!(
+            "Chess960 position index must be below {STARTING_POSITION_COUNT}, found {index}"
+        )));
+    }
+
+    let mut back_rank: [Option<PieceType>; BOARD_SIZE] = [None; BOARD_SIZE];
+    let (index, light_bishop) = (index / 4, index % 4);
+    let (index, dark_bishop) = (index / 4, index % 4);
+    let (knights, queen) = (index / 6, index % 6);
+
+    back_rank[light_bishop * 2 + 1] = Some(PieceType::Bishop);
+    back_rank[dark_bishop * 2] = Some(PieceType::Bishop);
+
+    let empty_files = |back_rank: &[Option<PieceType>; BOARD_SIZE]| -> Vec<usize> {
+        (0..BOARD_SIZE)
+            .filter(|file| back_rank[*file].is_none())
+            .collect()
+    };
+    back_rank[empty_files(&back_rank)[queen]] = Some(PieceType::Queen);
+
+    let (first_knight, second_knight) = KNIGHT_PLACEMENTS[knights];
+    let knight_files = empty_files(&back_rank);
+    back_rank[knight_files[first_knight]] = Some(PieceType::Knight);
+    back_rank[knight_files[second_knight]] = Some(PieceType::Knight);
+
+    // The king always ends up between the rooks on the last three squares
+    for (file, piece_type) in
+        empty_files(&back_rank)
+            .into_iter()
+            .zip([PieceType::Rook, PieceType::King, PieceType::Rook])
+    {
+        back_rank[file] = Some(piece_type);
+    }
+
+    Ok(back_rank.map(|piece_type| piece_type.unwrap_or(PieceType::Pawn)))
+}
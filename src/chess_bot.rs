@@ -1,11 +1,21 @@
+use crate::board_renderer::BoardRenderer;
 use crate::chess_dot_com_interface::ChessDotComInterface;
+use crate::engine_config::EngineConfig;
+use crate::game_analyzer::GameAnalyzer;
 use crate::game_board::GameBoard;
+use crate::pgn::PgnGame;
 use crate::piece::PieceColor;
-use crate::stockfish::Stockfish;
+use crate::search_info::SearchResult;
+use crate::search_limits::SearchLimits;
+use crate::uci_engine::UciEngine;
 use rand::Rng;
 use std::error::Error;
 use std::time::Duration;
-use tokio::time::sleep;
+use tokio::time::{interval, sleep};
+
+const BOT_NAME: &str = "FlyingChess1995XL";
+const OPPONENT_NAME: &str = "chess.com opponent";
+const MATCH_POLL_INTERVAL: Duration = Duration::from_millis(500);
 
 enum BotState {
     Start,
@@ -23,14 +33,38 @@ enum MatchState {
 
 pub struct ChessBot {
     site: ChessDotComInterface,
-    engine: Stockfish,
+    engine: UciEngine,
+    search_limits: SearchLimits,
+    candidate_count: u32,
+    analyzer: Option<GameAnalyzer>,
 }
 
 impl ChessBot {
-    pub async fn new() -> Result<ChessBot, Box<dyn Error>> {
+    pub async fn new(
+        engine_config: &EngineConfig,
+        search_limits: SearchLimits,
+        candidate_count: u32,
+        engine_options: &[(String, String)],
+        ponder: bool,
+        analyzer: Option<GameAnalyzer>,
+    ) -> Result<ChessBot, Box<dyn Error>> {
+        search_limits.ensure_finite()?;
+
+        let mut engine = UciEngine::new(engine_config).await?;
+        println!("Using engine {} by {}", engine.name(), engine.author());
+        for (name, value) in engine_options {
+            engine.set_option(name, value).await?;
+        }
+        if ponder {
+            engine.enable_ponder().await?;
+        }
+
         Ok(ChessBot {
             site: ChessDotComInterface::new().await?,
-            engine: Stockfish::new()?,
+            engine,
+            search_limits,
+            candidate_count,
+            analyzer,
         })
     }
 
@@ -68,13 +102,15 @@ impl ChessBot {
 
     async fn play_match(&mut self) -> Result<(), Box<dyn Error>> {
         let mut old_board_state = GameBoard::new()?;
+        let mut pgn_game = PgnGame::new();
         let mut match_state = MatchState::Start;
         let color = self.site.get_player_color().await?;
         let is_board_flipped = match color {
             PieceColor::White => false,
             PieceColor::Black => true,
         };
-        self.engine.reset()?;
+        let renderer = BoardRenderer::from(is_board_flipped);
+        self.engine.reset(&old_board_state).await?;
 
         loop {
             if !self.site.is_match_available().await {
@@ -103,30 +139,85 @@ impl ChessBot {
 
                     match old_board_state.get_move_from_difference(&current_board_state)? {
                         Some(move_made) => {
-                            println!("MOVE MADE: {move_made}");
-                            self.engine.record_move_played(&move_made);
+                            let move_made = old_board_state
+                                .find_legal_move(&move_made)?
+                                .ok_or_else(|| {
+                                    format!("Opponent move {move_made} is not a legal move")
+                                })?;
+                            println!(
+                                "MOVE MADE: {} ({move_made})",
+                                move_made.san_notation(&old_board_state)?
+                            );
+
+                            self.engine.record_opponent_move(&move_made).await?;
+
+                            old_board_state.apply_move(&move_made)?;
+                            println!("{}", renderer.render(&old_board_state, Some(&move_made))?);
+                            pgn_game.push_move(move_made, None);
                         }
                         None => println!("Starting position detected"),
                     }
 
-                    let best_move = self.engine.get_best_move()?;
-                    println!("BEST MOVE: {best_move}");
+                    if Self::record_termination(&old_board_state, &mut pgn_game)? {
+                        match_state = MatchState::MatchOver;
+                        continue;
+                    }
+
+                    let Some(search_result) = self.get_search_result().await? else {
+                        match_state = MatchState::MatchOver;
+                        continue;
+                    };
+                    let best_move = search_result.best_move;
+                    let best_move = old_board_state
+                        .find_legal_move(&best_move)?
+                        .ok_or_else(|| format!("Engine move {best_move} is not a legal move"))?;
+                    println!(
+                        "BEST MOVE: {} ({best_move})",
+                        best_move.san_notation(&old_board_state)?
+                    );
+                    println!("ENGINE EVAL: {}", search_result.info);
+                    let evaluation_comment = search_result.info.score.map(|score| {
+                        format!(
+                            "{}/{}",
+                            score.for_white(color),
+                            search_result.info.depth.unwrap_or_default()
+                        )
+                    });
 
                     println!("ABOUT TO PLAY MOVE");
                     let human_thinking_time = rand::rng().random_range(4500..15000);
-                    println!("A real human would clearly spend {} seconds thinking about this position", human_thinking_time / 1000);
+                    println!(
+                        "A real human would clearly spend {} seconds thinking about this position",
+                        human_thinking_time / 1000
+                    );
                     sleep(Duration::from_millis(human_thinking_time)).await;
                     self.site.play_move(&best_move, is_board_flipped).await?;
                     println!("MOVE HAS BEEN PLAYED");
 
+                    old_board_state.apply_move(&best_move)?;
+                    println!("{}", renderer.render(&old_board_state, Some(&best_move))?);
                     self.engine.record_move_played(&best_move);
-                    sleep(Duration::from_millis(150)).await;
-                    old_board_state.set_state(&self.site.get_current_board_state().await?); // TODO: CAN OPTIMIZE BY REMOVING NETWORK CALL
+                    pgn_game.push_move(best_move, evaluation_comment);
+
+                    if Self::record_termination(&old_board_state, &mut pgn_game)? {
+                        match_state = MatchState::MatchOver;
+                        continue;
+                    }
+
+                    self.engine
+                        .ponder_expected_reply(
+                            &old_board_state,
+                            search_result.ponder_move.as_ref(),
+                            &self.search_limits,
+                            self.candidate_count,
+                        )
+                        .await?;
 
                     match_state = MatchState::WaitingForTurn;
                     println!("Bro's really taking this long...");
                 }
                 MatchState::MatchOver => {
+                    self.engine.abandon_search().await;
                     println!("gg2ez");
                     break;
                 }
@@ -135,6 +226,82 @@ impl ChessBot {
             sleep(Duration::from_millis(500)).await;
         }
 
+        Self::archive_match(&mut pgn_game, color)?;
+
+        if let Some(analyzer) = &mut self.analyzer {
+            println!("{}", analyzer.analyze(&pgn_game).await?);
+        }
+        Ok(())
+    }
+
+    async fn get_search_result(&mut self) -> Result<Option<SearchResult>, Box<dyn Error>> {
+        self.engine
+            .search_unless_pondered(&self.search_limits, self.candidate_count)
+            .await?;
+
+        // Keep an eye on the site while the engine thinks and give up if the match ends. The
+        // wait is only dropped to give up, since it may be part way through restarting the engine
+        let mut match_poll = interval(MATCH_POLL_INTERVAL);
+        // First tick completes right away, the site was just checked before the search started
+        match_poll.tick().await;
+        let search_result = {
+            let search = self.engine.wait_for_search();
+            tokio::pin!(search);
+            loop {
+                tokio::select! {
+                    search_result = &mut search => break Some(search_result?),
+                    _ = match_poll.tick() => {
+                        if !self.site.is_match_available().await {
+                            break None;
+                        }
+                    }
+                }
+            }
+        };
+        let Some(search_result) = search_result else {
+            self.engine.abandon_search().await;
+            return Ok(None);
+        };
+
+        // Top candidate of a multipv search is the engine's best move
+        if self.candidate_count > 1 {
+            for (index, candidate) in search_result.candidates.iter().enumerate() {
+                println!("CANDIDATE {}: {candidate}", index + 1);
+            }
+        }
+
+        Ok(Some(search_result))
+    }
+
+    fn record_termination(
+        board: &GameBoard,
+        pgn_game: &mut PgnGame,
+    ) -> Result<bool, Box<dyn Error>> {
+        match board.get_termination()? {
+            Some(termination) => {
+                println!("GAME OVER: {termination}");
+                pgn_game.result = termination.pgn_result().to_string();
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    fn archive_match(pgn_game: &mut PgnGame, color: PieceColor) -> Result<(), Box<dyn Error>> {
+        let (white_name, black_name) = match color {
+            PieceColor::White => (BOT_NAME, OPPONENT_NAME),
+            PieceColor::Black => (OPPONENT_NAME, BOT_NAME),
+        };
+
+        pgn_game.set_tag("Event", "Chess.com training game");
+        pgn_game.set_tag("Site", "https://www.chess.com");
+        pgn_game.set_tag("Round", "-");
+        pgn_game.set_tag("White", white_name);
+        pgn_game.set_tag("Black", black_name);
+
+        let path = pgn_game.archive()?;
+        println!("Saved game to {}", path.display());
+
         Ok(())
     }
 }
@@ -119,6 +119,7 @@ impl ChessDotComInterface {
         Ok(())
     }
 
+    #[allow(clippy::redundant_pattern_matching)]
     pub async fn is_match_available(&self) -> bool {
         // The draw button is only present when a game is in progress
         // #board-layout-sidebar --> nearest parent with an id to limit search
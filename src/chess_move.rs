@@ -1,8 +1,12 @@
-use crate::piece::PieceType;
+use crate::game_board::GameBoard;
+use crate::packed_move::{MoveFlag, PackedMove};
+use crate::piece::{Piece, PieceColor, PieceType};
 use crate::square::Square;
+use anyhow::Context;
 use std::error::Error;
 use std::fmt::{Display, Formatter};
 
+#[derive(Clone)]
 pub struct ChessMove {
     pub start: Square,
     pub end: Square,
@@ -13,15 +17,31 @@ impl ChessMove {
         Self { start, end }
     }
 
-    pub fn from_uci_notation(notation: &String) -> Result<Self, Box<dyn Error>> {
-        // e2e4
+    pub fn from_uci_notation(notation: &str) -> Result<Self, Box<dyn Error>> {
+        // e2e4 or e7e8q
         let mut chars = notation.chars();
 
         let start_notation: String = chars.by_ref().take(2).collect();
-        let start = Square::from_uci_notation(&start_notation)?;
+        let mut start = Square::from_uci_notation(&start_notation)?;
 
         let end_notation: String = chars.by_ref().take(2).collect();
-        let end = Square::from_uci_notation(&end_notation)?;
+        let mut end = Square::from_uci_notation(&end_notation)?;
+
+        // Promotions only land on the back ranks, so the end rank tells us the pawn color
+        if let Some(promotion_char) = chars.next() {
+            let piece_type = Piece::uci_char_to_promotion_type(promotion_char)?;
+            let color = match end.rank_index()? {
+                7 => PieceColor::White,
+                0 => PieceColor::Black,
+                bad_rank => {
+                    return Err(Box::from(format!(
+                        "Found promotion '{promotion_char}' to non back rank index {bad_rank} in: {notation}"
+                    )));
+                }
+            };
+            start.piece = Some(Piece::from(color, PieceType::Pawn));
+            end.piece = Some(Piece::from(color, piece_type));
+        }
 
         Ok(Self { start, end })
     }
@@ -38,10 +58,221 @@ impl ChessMove {
         )
     }
 
-    fn get_promotion_type(&self) -> Option<PieceType> {
-        match self.start.piece.as_ref()?.piece_type == self.end.piece.as_ref()?.piece_type {
-            true => None,
-            false => Some(self.end.piece.as_ref()?.piece_type),
+    pub fn from_san_notation(notation: &str, board: &GameBoard) -> Result<Self, Box<dyn Error>> {
+        // Nbxd7+, exd6e.p., e8=Q#, O-O-O, Qh4xe1!?
+        let legal_moves = board.legal_moves()?;
+        let trimmed_notation = notation
+            .trim_end_matches("e.p.")
+            .trim_end_matches(['+', '#', '!', '?']);
+
+        let castle_direction = match trimmed_notation {
+            "O-O" | "0-0" => Some(true),
+            "O-O-O" | "0-0-0" => Some(false),
+            _ => None,
+        };
+        if let Some(is_king_side) = castle_direction {
+            for legal_move in legal_moves {
+                if legal_move.is_castle(board)?
+                    && (legal_move.end.file_index()? > legal_move.start.file_index()?)
+                        == is_king_side
+                {
+                    return Ok(legal_move);
+                }
+            }
+            return Err(Box::from(format!("Castle {notation} is not a legal move")));
+        }
+
+        let mut chars: Vec<char> = trimmed_notation
+            .chars()
+            .filter(|c| !matches!(c, 'x' | ':' | '-'))
+            .collect();
+
+        let piece_type = match chars.first() {
+            Some(c) if c.is_ascii_uppercase() => {
+                let piece_type = PieceType::from_san_char(*c)?;
+                chars.remove(0);
+                piece_type
+            }
+            _ => PieceType::Pawn,
+        };
+
+        // Promotion piece may or may not have an '=' in front of it
+        let promotion_type = match chars.last() {
+            Some(c) if c.is_ascii_uppercase() => {
+                let promotion_type = PieceType::from_san_char(*c)?;
+                chars.pop();
+                if chars.last() == Some(&'=') {
+                    chars.pop();
+                }
+                Some(promotion_type)
+            }
+            _ => None,
+        };
+
+        if chars.len() < 2 {
+            return Err(Box::from(format!(
+                "Could not find destination square in SAN: {notation}"
+            )));
+        }
+        let end_notation: String = chars.split_off(chars.len() - 2).iter().collect();
+        let end = Square::from_uci_notation(&end_notation)?;
+        let end_rank = end.rank_index()?;
+        let end_file = end.file_index()?;
+
+        // Whatever is left over is the disambiguation file and / or rank
+        let mut start_file: Option<usize> = None;
+        let mut start_rank: Option<usize> = None;
+        for c in chars {
+            match c {
+                'a'..='h' => start_file = Some(c as usize - 'a' as usize),
+                '1'..='8' => start_rank = Some(Square::chess_dot_com_index_char_to_index(c)?),
+                _ => {
+                    return Err(Box::from(format!(
+                        "Could not parse char '{c}' in SAN: {notation}"
+                    )));
+                }
+            }
+        }
+
+        let mut matching_moves: Vec<ChessMove> = Vec::new();
+        for legal_move in legal_moves {
+            let is_matching_piece = legal_move
+                .start
+                .piece
+                .as_ref()
+                .is_some_and(|piece| piece.piece_type == piece_type);
+
+            if is_matching_piece
+                && legal_move.end.rank_index()? == end_rank
+                && legal_move.end.file_index()? == end_file
+                && legal_move.get_promotion_type() == promotion_type
+                && start_file.is_none_or(|file| legal_move.start.file_index().ok() == Some(file))
+                && start_rank.is_none_or(|rank| legal_move.start.rank_index().ok() == Some(rank))
+            {
+                matching_moves.push(legal_move);
+            }
+        }
+
+        match matching_moves.len() {
+            0 => Err(Box::from(format!(
+                "SAN move {notation} is not a legal move"
+            ))),
+            1 => Ok(matching_moves.remove(0)),
+            count => Err(Box::from(format!(
+                "SAN move {notation} is ambiguous between {count} legal moves"
+            ))),
+        }
+    }
+
+    pub fn san_notation(&self, board: &GameBoard) -> Result<String, Box<dyn Error>> {
+        let moving_piece = board
+            .piece_at(&self.start)?
+            .context(format!("No piece found to move on {}", self.start))?
+            .clone();
+        let start_file = self.start.file_index()?;
+        let end_file = self.end.file_index()?;
+
+        let mut notation = String::new();
+
+        if self.is_castle(board)? {
+            notation.push_str(match end_file > start_file {
+                true => "O-O",
+                false => "O-O-O",
+            });
+        } else {
+            // Pawns can only change files by capturing, which also covers en passant
+            let is_capture = board.piece_at(&self.end)?.is_some()
+                || (moving_piece.piece_type == PieceType::Pawn && start_file != end_file);
+
+            match moving_piece.piece_type {
+                PieceType::Pawn if is_capture => {
+                    notation.push_str(Square::index_to_file(start_file)?.as_str())
+                }
+                PieceType::Pawn => {}
+                _ => {
+                    notation.push(moving_piece.piece_type.san_char());
+                    notation.push_str(self.get_san_disambiguation(board, &moving_piece)?.as_str());
+                }
+            }
+
+            if is_capture {
+                notation.push('x');
+            }
+            notation.push_str(self.end.uci_notation().as_str());
+
+            if let Some(promotion_type) = self.get_promotion_type() {
+                notation.push('=');
+                notation.push(promotion_type.san_char());
+            }
+        }
+
+        let mut board_after_move = board.clone();
+        board_after_move.apply_move(self)?;
+        let opponent_color = moving_piece.color.opposite();
+        if board_after_move.is_in_check(opponent_color) {
+            match board_after_move.legal_moves()?.is_empty() {
+                true => notation.push('#'),
+                false => notation.push('+'),
+            }
+        }
+
+        Ok(notation)
+    }
+
+    pub fn get_promotion_type(&self) -> Option<PieceType> {
+        // Only a pawn turns into something else, a scraped end square can hold whatever was captured there
+        let start_piece = self.start.piece.as_ref()?;
+        let end_piece = self.end.piece.as_ref()?;
+        match (start_piece.piece_type, end_piece.piece_type) {
+            (
+                PieceType::Pawn,
+                PieceType::Knight | PieceType::Bishop | PieceType::Rook | PieceType::Queen,
+            ) if start_piece.color == end_piece.color => Some(end_piece.piece_type),
+            _ => None,
+        }
+    }
+
+    fn is_castle(&self, board: &GameBoard) -> Result<bool, Box<dyn Error>> {
+        // Chess960 castles are written as the king taking its own rook, so the board has to decide
+        Ok(PackedMove::from_chess_move(self, board)?.flag() == MoveFlag::Castle)
+    }
+
+    fn get_san_disambiguation(
+        &self,
+        board: &GameBoard,
+        moving_piece: &Piece,
+    ) -> Result<String, Box<dyn Error>> {
+        let start_notation = self.start.uci_notation();
+        let end_notation = self.end.uci_notation();
+
+        // Other pieces of the same type that can also reach the end square
+        let mut ambiguous_starts: Vec<Square> = Vec::new();
+        for legal_move in board.legal_moves()? {
+            if legal_move.end.uci_notation() == end_notation
+                && legal_move.start.uci_notation() != start_notation
+                && legal_move.start.piece.as_ref() == Some(moving_piece)
+            {
+                ambiguous_starts.push(legal_move.start);
+            }
+        }
+
+        if ambiguous_starts.is_empty() {
+            return Ok(String::new());
+        }
+
+        let start_rank = self.start.rank_index()?;
+        let start_file = self.start.file_index()?;
+        let mut shares_rank = false;
+        let mut shares_file = false;
+        for ambiguous_start in &ambiguous_starts {
+            shares_rank |= ambiguous_start.rank_index()? == start_rank;
+            shares_file |= ambiguous_start.file_index()? == start_file;
+        }
+
+        match (shares_file, shares_rank) {
+            (false, _) => Square::index_to_file(start_file),
+            (true, false) => Square::index_to_rank(start_rank),
+            (true, true) => Ok(start_notation),
         }
     }
 }
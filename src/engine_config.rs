@@ -0,0 +1,59 @@
+use anyhow::Context;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+
+const BUNDLED_ENGINE_NAME: &str = "stockfish";
+const BUNDLED_ENGINE_PATH: &str = "./bin/stockfish_engine";
+
+#[derive(Clone, PartialEq)]
+pub struct EngineConfig {
+    pub name: String,
+    pub path: PathBuf,
+    pub args: Vec<String>,
+}
+
+impl EngineConfig {
+    pub fn bundled_stockfish() -> Self {
+        Self {
+            name: BUNDLED_ENGINE_NAME.to_string(),
+            path: PathBuf::from(BUNDLED_ENGINE_PATH),
+            args: Vec::new(),
+        }
+    }
+
+    pub fn from_path(path: &str) -> Self {
+        Self {
+            name: path.to_string(),
+            path: PathBuf::from(path),
+            args: Vec::new(),
+        }
+    }
+
+    pub fn from_registry_line(line: &str) -> Result<Self, Box<dyn Error>> {
+        // lc0: /usr/local/bin/lc0 --weights=./bin/maia-1500.pb.gz
+        let (name, command) = line.split_once(':').context(format!(
+            "Expected name: path [args] in engine registry line: {line}"
+        ))?;
+        let mut command_tokens = command.split_whitespace();
+        let path = command_tokens.next().context(format!(
+            "Missing engine path in engine registry line: {line}"
+        ))?;
+
+        Ok(Self {
+            name: name.trim().to_string(),
+            path: PathBuf::from(path),
+            args: command_tokens.map(|arg| arg.to_string()).collect(),
+        })
+    }
+}
+
+impl Display for EngineConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.name, self.path.display())?;
+        for arg in &self.args {
+            write!(f, " {arg}")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,54 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::process::ExitStatus;
+
+#[derive(Debug)]
+pub enum EngineError {
+    Crashed {
+        engine: String,
+        status: Option<ExitStatus>,
+    },
+    Timeout {
+        engine: String,
+        waiting_for: String,
+    },
+    NotSearching {
+        engine: String,
+    },
+    AlreadySearching {
+        engine: String,
+    },
+}
+
+impl EngineError {
+    pub fn is_recoverable_by_restart(&self) -> bool {
+        // A dead or hung process is worth relaunching, misuse of the API is not
+        matches!(self, Self::Crashed { .. } | Self::Timeout { .. })
+    }
+}
+
+impl Display for EngineError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Crashed {
+                engine,
+                status: Some(status),
+            } => write!(f, "Engine {engine} exited unexpectedly with {status}"),
+            Self::Crashed {
+                engine,
+                status: None,
+            } => write!(
+                f,
+                "Engine {engine} stopped responding and closed its output"
+            ),
+            Self::Timeout {
+                engine,
+                waiting_for,
+            } => write!(f, "Engine {engine} did not send {waiting_for} in time"),
+            Self::NotSearching { engine } => write!(f, "Engine {engine} has no search running"),
+            Self::AlreadySearching { engine } => write!(f, "Engine {engine} is already searching"),
+        }
+    }
+}
+
+impl Error for EngineError {}
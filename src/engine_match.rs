@@ -0,0 +1,381 @@
+use crate::chess_move::ChessMove;
+use crate::engine_config::EngineConfig;
+use crate::game_board::GameBoard;
+use crate::match_statistics::MatchStatistics;
+use crate::pgn::PgnGame;
+use crate::piece::PieceColor;
+use crate::polyglot_book::PolyglotBook;
+use crate::search_limits::SearchLimits;
+use crate::uci_engine::UciEngine;
+use anyhow::Context;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+const MAX_GAME_PLIES: usize = 400;
+const MAX_BOOK_PLIES: usize = 16;
+const RESIGN_CENTIPAWNS: i32 = 1000;
+const RESIGN_PLIES: u32 = 6;
+const DRAW_CENTIPAWNS: i32 = 10;
+const DRAW_PLIES: u32 = 12;
+const DRAW_MIN_PLY: usize = 80;
+
+pub struct EngineMatch {
+    engines: [UciEngine; 2],
+    engine_names: [String; 2],
+    limits: SearchLimits,
+    openings: Vec<GameBoard>,
+    book: Option<PolyglotBook>,
+    game_count: u32,
+    pgn_path: PathBuf,
+}
+
+struct GameOutcome {
+    result: &'static str,
+    termination: String,
+}
+
+impl EngineMatch {
+    pub async fn new(
+        engine_configs: [&EngineConfig; 2],
+        engine_options: [&[(String, String)]; 2],
+        limits: SearchLimits,
+        openings: Vec<GameBoard>,
+        book: Option<PolyglotBook>,
+        game_count: u32,
+        pgn_path: PathBuf,
+    ) -> Result<EngineMatch, Box<dyn Error>> {
+        limits.ensure_finite()?;
+        if limits.moves_to_go == Some(0) {
+            return Err(Box::from("Moves to go has to be at least one move"));
+        }
+        if openings.is_empty() {
+            return Err(Box::from("An engine match needs at least one opening"));
+        }
+
+        let mut engines = [
+            UciEngine::new(engine_configs[0]).await?,
+            UciEngine::new(engine_configs[1]).await?,
+        ];
+        for (engine, options) in engines.iter_mut().zip(engine_options) {
+            for (name, value) in options {
+                engine.set_option(name, value).await?;
+            }
+        }
+
+        // Two settings of the same engine still need to be told apart in the results
+        let engine_names = match engine_configs[0].name == engine_configs[1].name {
+            true => [
+                format!("{} #1", engine_configs[0].name),
+                format!("{} #2", engine_configs[1].name),
+            ],
+            false => [
+                engine_configs[0].name.clone(),
+                engine_configs[1].name.clone(),
+            ],
+        };
+
+        Ok(EngineMatch {
+            engines,
+            engine_names,
+            limits,
+            openings,
+            book,
+            game_count,
+            pgn_path,
+        })
+    }
+
+    pub fn load_openings(path: &Path) -> Result<Vec<GameBoard>, Box<dyn Error>> {
+        // PGN openings are played out to their final position, anything else is read as FEN/EPD lines
+        if path.extension().is_some_and(|extension| extension == "pgn") {
+            return PgnGame::read_file(path)?
+                .iter()
+                .map(|game| {
+                    let mut board = game.get_starting_board()?;
+                    for pgn_move in &game.moves {
+                        board.apply_move(&pgn_move.chess_move)?;
+                    }
+                    Ok(board)
+                })
+                .collect();
+        }
+
+        let contents = fs::read_to_string(path)
+            .context(format!("Failed to read openings file: {}", path.display()))?;
+        contents
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                // EPD lines carry operations instead of move clocks after the first four fields
+                let fields: Vec<&str> = line.split_whitespace().collect();
+                let has_clocks = fields.len() >= 6
+                    && fields[4..6]
+                        .iter()
+                        .all(|field| field.parse::<u32>().is_ok());
+                let field_count = if has_clocks { 6 } else { fields.len().min(4) };
+                GameBoard::from_fen(fields[..field_count].join(" ").as_str())
+            })
+            .collect()
+    }
+
+    pub async fn run(&mut self) -> Result<MatchStatistics, Box<dyn Error>> {
+        let mut statistics = MatchStatistics::default();
+
+        let mut book_moves = Vec::new();
+        for game_index in 0..self.game_count {
+            // Both games of a pair follow the same book line so the colors really are swapped
+            if game_index % 2 == 0 {
+                book_moves = self.get_book_line(game_index)?;
+            }
+            let (pgn_game, first_engine_color) = self.play_game(game_index, &book_moves).await?;
+            pgn_game.append_file(&self.pgn_path)?;
+
+            let first_engine_score = match (pgn_game.result.as_str(), first_engine_color) {
+                ("1-0", PieceColor::White) | ("0-1", PieceColor::Black) => 1.0,
+                ("1-0", PieceColor::Black) | ("0-1", PieceColor::White) => 0.0,
+                _ => 0.5,
+            };
+            statistics.record_result(first_engine_score);
+
+            println!(
+                "GAME {}: {} vs {} {} ({})",
+                game_index + 1,
+                pgn_game.get_tag("White").unwrap_or_default(),
+                pgn_game.get_tag("Black").unwrap_or_default(),
+                pgn_game.result,
+                pgn_game.get_tag("Termination").unwrap_or_default()
+            );
+            println!(
+                "{} vs {}: {statistics}",
+                self.engine_names[0], self.engine_names[1]
+            );
+        }
+
+        println!("Saved games to {}", self.pgn_path.display());
+        Ok(statistics)
+    }
+
+    fn get_book_line(&self, game_index: u32) -> Result<Vec<ChessMove>, Box<dyn Error>> {
+        let Some(book) = &self.book else {
+            return Ok(Vec::new());
+        };
+
+        let mut board = self.get_opening(game_index).clone();
+        let mut book_moves = Vec::new();
+        while book_moves.len() < MAX_BOOK_PLIES {
+            let Some(book_move) = board.get_book_move(book)? else {
+                break;
+            };
+            board.apply_move(&book_move)?;
+            book_moves.push(book_move);
+        }
+
+        Ok(book_moves)
+    }
+
+    fn get_opening(&self, game_index: u32) -> &GameBoard {
+        // Every opening is played twice so each engine gets both sides of it
+        &self.openings[(game_index / 2) as usize % self.openings.len()]
+    }
+
+    async fn play_game(
+        &mut self,
+        game_index: u32,
+        book_moves: &[ChessMove],
+    ) -> Result<(PgnGame, PieceColor), Box<dyn Error>> {
+        let mut board = self.get_opening(game_index).clone();
+        let first_engine_color = match game_index % 2 {
+            0 => PieceColor::White,
+            _ => PieceColor::Black,
+        };
+        let engine_index_for = |color: PieceColor| usize::from(color != first_engine_color);
+
+        let mut pgn_game = PgnGame::new();
+        pgn_game.set_tag("Event", "Engine match");
+        pgn_game.set_tag("Site", "Local engine match");
+        pgn_game.set_tag(
+            "Date",
+            PgnGame::get_date_tag_value(SystemTime::now()).as_str(),
+        );
+        pgn_game.set_tag("Round", (game_index + 1).to_string().as_str());
+        pgn_game.set_tag(
+            "White",
+            self.engine_names[engine_index_for(PieceColor::White)].as_str(),
+        );
+        pgn_game.set_tag(
+            "Black",
+            self.engine_names[engine_index_for(PieceColor::Black)].as_str(),
+        );
+        pgn_game.set_starting_board_tags(&board)?;
+
+        for engine in &mut self.engines {
+            engine.reset(&board).await?;
+        }
+        for book_move in book_moves {
+            board.apply_move(book_move)?;
+            for engine in &mut self.engines {
+                engine.record_move_played(book_move);
+            }
+            pgn_game.push_move(book_move.clone(), Some(String::from("book")));
+        }
+
+        // Clocks run down for real when the match is played with a time control, and each side
+        // counts down its own moves to go until its clock is topped up
+        let mut limits = self.limits.clone();
+        let mut moves_to_go = [self.limits.moves_to_go; 2];
+        let mut resign_plies = 0;
+        let mut winning_direction = 0;
+        let mut draw_plies = 0;
+
+        let outcome = loop {
+            if let Some(termination) = board.get_termination()? {
+                break GameOutcome {
+                    result: termination.pgn_result(),
+                    termination: termination.to_string(),
+                };
+            }
+            if pgn_game.moves.len() >= MAX_GAME_PLIES {
+                break GameOutcome {
+                    result: "1/2-1/2",
+                    termination: String::from("Draw by move limit adjudication"),
+                };
+            }
+
+            let side_to_move = board.side_to_move();
+            let clock_index = usize::from(side_to_move == PieceColor::Black);
+            limits.moves_to_go = moves_to_go[clock_index];
+            let engine = &mut self.engines[engine_index_for(side_to_move)];
+            let search_started = Instant::now();
+            engine.start_search(&limits, 1).await?;
+            let search_result = engine.wait_for_search().await?;
+            let search_time = search_started.elapsed();
+
+            if Self::update_clock(
+                &mut limits,
+                &self.limits,
+                &mut moves_to_go[clock_index],
+                side_to_move,
+                search_time,
+            ) {
+                break Self::loss_for(side_to_move, "loses on time");
+            }
+            let Some(best_move) = board.find_legal_move(&search_result.best_move)? else {
+                break Self::loss_for(side_to_move, "loses by playing an illegal move");
+            };
+
+            let white_score = search_result
+                .info
+                .score
+                .map(|score| score.for_white(side_to_move));
+            let evaluation_comment = white_score.map(|score| {
+                format!(
+                    "{score}/{} {:.2}s",
+                    search_result.info.depth.unwrap_or_default(),
+                    search_time.as_secs_f64()
+                )
+            });
+
+            board.apply_move(&best_move)?;
+            for engine in &mut self.engines {
+                engine.record_move_played(&best_move);
+            }
+            pgn_game.push_move(best_move, evaluation_comment);
+
+            // Adjudicate once the evaluation has been decisive or dead level for long enough
+            let white_centipawns = white_score.map(|score| score.to_centipawns());
+            resign_plies = match white_centipawns {
+                Some(centipawns)
+                    if centipawns.abs() >= RESIGN_CENTIPAWNS
+                        && centipawns.signum() == winning_direction =>
+                {
+                    resign_plies + 1
+                }
+                Some(centipawns) if centipawns.abs() >= RESIGN_CENTIPAWNS => 1,
+                _ => 0,
+            };
+            winning_direction = white_centipawns.map_or(0, i32::signum);
+            draw_plies = match white_centipawns {
+                Some(centipawns)
+                    if centipawns.abs() <= DRAW_CENTIPAWNS
+                        && pgn_game.moves.len() >= DRAW_MIN_PLY =>
+                {
+                    draw_plies + 1
+                }
+                _ => 0,
+            };
+
+            if resign_plies >= RESIGN_PLIES {
+                let losing_color = match white_centipawns.unwrap_or_default() > 0 {
+                    true => PieceColor::Black,
+                    false => PieceColor::White,
+                };
+                break Self::loss_for(losing_color, "resigns by adjudication");
+            }
+            if draw_plies >= DRAW_PLIES {
+                break GameOutcome {
+                    result: "1/2-1/2",
+                    termination: String::from("Draw by adjudication"),
+                };
+            }
+        };
+
+        pgn_game.result = outcome.result.to_string();
+        pgn_game.set_tag("Termination", outcome.termination.as_str());
+        Ok((pgn_game, first_engine_color))
+    }
+
+    fn update_clock(
+        limits: &mut SearchLimits,
+        time_control: &SearchLimits,
+        moves_to_go: &mut Option<u32>,
+        color: PieceColor,
+        search_time: Duration,
+    ) -> bool {
+        // Returns true when the side to move ran out of time
+        let (clock, increment, base_time) = match color {
+            PieceColor::White => (
+                &mut limits.white_time,
+                limits.white_increment,
+                time_control.white_time,
+            ),
+            PieceColor::Black => (
+                &mut limits.black_time,
+                limits.black_increment,
+                time_control.black_time,
+            ),
+        };
+        let Some(remaining) = clock else {
+            return false;
+        };
+        if search_time > *remaining {
+            return true;
+        }
+
+        *remaining = *remaining - search_time + increment.unwrap_or_default();
+
+        // Playing the last move of a time control (e.g. 40 moves in 5 minutes) starts the next one
+        match moves_to_go {
+            Some(moves_left) if *moves_left > 1 => *moves_left -= 1,
+            Some(_) => {
+                *remaining += base_time.unwrap_or_default();
+                *moves_to_go = time_control.moves_to_go;
+            }
+            None => {}
+        }
+        false
+    }
+
+    fn loss_for(color: PieceColor, reason: &str) -> GameOutcome {
+        let (result, name) = match color {
+            PieceColor::White => ("0-1", "White"),
+            PieceColor::Black => ("1-0", "Black"),
+        };
+        GameOutcome {
+            result,
+            termination: format!("{name} {reason}"),
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use crate::engine_config::EngineConfig;
+use anyhow::Context;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+pub struct EngineRegistry {
+    engines: Vec<EngineConfig>,
+}
+
+impl EngineRegistry {
+    pub fn from_file(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let mut engines = vec![EngineConfig::bundled_stockfish()];
+
+        // Without a registry file only the bundled Stockfish is available
+        if !path.exists() {
+            return Ok(Self { engines });
+        }
+
+        let contents = fs::read_to_string(path).context(format!(
+            "Failed to read engine registry: {}",
+            path.display()
+        ))?;
+        for line in contents.lines().map(|line| line.trim()) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            // Registry entries replace the bundled engine when they share its name
+            let engine = EngineConfig::from_registry_line(line)?;
+            engines.retain(|existing| existing.name != engine.name);
+            engines.push(engine);
+        }
+
+        Ok(Self { engines })
+    }
+
+    pub fn engines(&self) -> &[EngineConfig] {
+        &self.engines
+    }
+
+    pub fn get(&self, name_or_path: &str) -> Result<EngineConfig, Box<dyn Error>> {
+        if let Some(engine) = self
+            .engines
+            .iter()
+            .find(|engine| engine.name == name_or_path)
+        {
+            return Ok(engine.clone());
+        }
+
+        // Anything that is not registered can still be launched straight from its path
+        match Path::new(name_or_path).exists() {
+            true => Ok(EngineConfig::from_path(name_or_path)),
+            false => Err(Box::from(format!(
+                "No registered engine or executable named: {name_or_path}"
+            ))),
+        }
+    }
+}
@@ -0,0 +1,188 @@
+use crate::analysis_report::{AnalysisReport, MoveAnalysis, MoveClassification};
+use crate::engine_config::EngineConfig;
+use crate::game_board::GameBoard;
+use crate::game_termination::GameTermination;
+use crate::packed_move::PackedMove;
+use crate::pgn::PgnGame;
+use crate::piece::PieceColor;
+use crate::search_info::Score;
+use crate::search_limits::SearchLimits;
+use crate::uci_engine::UciEngine;
+use std::collections::HashMap;
+use std::error::Error;
+
+const DEFAULT_ANALYSIS_DEPTH: u32 = 14;
+const EVALUATION_CAP_CENTIPAWNS: i32 = 1500;
+const WIN_PERCENT_SLOPE: f64 = 0.00368208;
+
+pub struct GameAnalyzer {
+    engine: UciEngine,
+    limits: SearchLimits,
+    evaluation_cache: HashMap<u64, PositionEvaluation>,
+}
+
+#[derive(Clone)]
+struct PositionEvaluation {
+    score: Score,
+    white_centipawns: i32,
+    best_move: Option<PackedMove>,
+}
+
+impl GameAnalyzer {
+    pub async fn new(
+        engine_config: &EngineConfig,
+        engine_options: &[(String, String)],
+        limits: SearchLimits,
+    ) -> Result<GameAnalyzer, Box<dyn Error>> {
+        limits.ensure_finite()?;
+
+        let mut engine = UciEngine::new(engine_config).await?;
+        for (name, value) in engine_options {
+            engine.set_option(name, value).await?;
+        }
+
+        Ok(GameAnalyzer {
+            engine,
+            limits,
+            evaluation_cache: HashMap::new(),
+        })
+    }
+
+    pub fn default_limits() -> SearchLimits {
+        SearchLimits::from_depth(DEFAULT_ANALYSIS_DEPTH)
+    }
+
+    pub async fn analyze(&mut self, pgn_game: &PgnGame) -> Result<AnalysisReport, Box<dyn Error>> {
+        let mut board = pgn_game.get_starting_board()?;
+        self.engine.reset(&board).await?;
+
+        // Each position is searched once and serves as the "after" of one move and the "before" of the next
+        let mut before = self.evaluate(&board).await?;
+        let mut moves = Vec::new();
+        for pgn_move in &pgn_game.moves {
+            let played_move = board
+                .find_legal_move(&pgn_move.chess_move)?
+                .ok_or_else(|| format!("Move {} is not legal in the game", pgn_move.chess_move))?;
+            let color = board.side_to_move();
+            let move_number = board.fullmove_number();
+            let san = played_move.san_notation(&board)?;
+            let best_move_san = match before.best_move {
+                Some(best_move) if board.legal_packed_moves()?.contains(&best_move) => {
+                    board.to_chess_move(best_move).san_notation(&board)?
+                }
+                Some(best_move) => best_move.uci_notation(),
+                None => String::from("?"),
+            };
+            let is_best_move =
+                before.best_move == Some(PackedMove::from_chess_move(&played_move, &board)?);
+
+            board.apply_move(&played_move)?;
+            self.engine.record_move_played(&played_move);
+            let after = self.evaluate(&board).await?;
+
+            // Losses are measured from the mover's point of view and the engine's own move costs nothing
+            let (mover_before, mover_after) = match color {
+                PieceColor::White => (before.white_centipawns, after.white_centipawns),
+                PieceColor::Black => (-before.white_centipawns, -after.white_centipawns),
+            };
+            let mover_after = if is_best_move {
+                mover_before
+            } else {
+                mover_after
+            };
+            let centipawn_loss = (mover_before - mover_after).max(0);
+
+            moves.push(MoveAnalysis {
+                move_number,
+                color,
+                san,
+                best_move_san,
+                evaluation: after.score,
+                centipawn_loss,
+                accuracy: Self::move_accuracy(mover_before, mover_after),
+                classification: MoveClassification::from_centipawn_loss(
+                    centipawn_loss,
+                    is_best_move,
+                ),
+            });
+            before = after;
+        }
+
+        Ok(AnalysisReport {
+            white_name: pgn_game.get_tag("White").unwrap_or("White").to_string(),
+            black_name: pgn_game.get_tag("Black").unwrap_or("Black").to_string(),
+            result: pgn_game.result.clone(),
+            moves,
+        })
+    }
+
+    async fn evaluate(&mut self, board: &GameBoard) -> Result<PositionEvaluation, Box<dyn Error>> {
+        // Finished games have nothing left to search
+        match board.get_termination()? {
+            Some(GameTermination::Checkmate { winner }) => {
+                let white_centipawns = match winner {
+                    PieceColor::White => EVALUATION_CAP_CENTIPAWNS,
+                    PieceColor::Black => -EVALUATION_CAP_CENTIPAWNS,
+                };
+                return Ok(PositionEvaluation {
+                    score: Score::Mate(0),
+                    white_centipawns,
+                    best_move: None,
+                });
+            }
+            Some(_) => {
+                return Ok(PositionEvaluation {
+                    score: Score::Centipawns(0),
+                    white_centipawns: 0,
+                    best_move: None,
+                });
+            }
+            None => {}
+        }
+
+        // Transpositions and openings shared between games only need to be searched once
+        if let Some(evaluation) = self.evaluation_cache.get(&board.zobrist_key()) {
+            return Ok(evaluation.clone());
+        }
+
+        let top_line = self
+            .engine
+            .analyse_multipv(1, &self.limits)
+            .await?
+            .into_iter()
+            .next()
+            .unwrap_or_default();
+        let score = top_line
+            .score
+            .unwrap_or(Score::Centipawns(0))
+            .for_white(board.side_to_move());
+
+        // Capping keeps a slower mate or a won position getting "more won" from counting as a loss
+        let evaluation = PositionEvaluation {
+            score,
+            white_centipawns: score
+                .to_centipawns()
+                .clamp(-EVALUATION_CAP_CENTIPAWNS, EVALUATION_CAP_CENTIPAWNS),
+            best_move: top_line
+                .principal_variation
+                .first()
+                .map(|best_move| PackedMove::from_chess_move(best_move, board))
+                .transpose()?,
+        };
+        self.evaluation_cache
+            .insert(board.zobrist_key(), evaluation.clone());
+        Ok(evaluation)
+    }
+
+    fn move_accuracy(mover_before: i32, mover_after: i32) -> f64 {
+        // Accuracy drops off with the winning chances thrown away, so the same centipawn loss
+        // hurts far more in a balanced position than in one that is already decided
+        let win_percent_loss =
+            (Self::win_percent(mover_before) - Self::win_percent(mover_after)).max(0.0);
+        (103.1668 * (-0.04354 * win_percent_loss).exp() - 3.1669).clamp(0.0, 100.0)
+    }
+
+    fn win_percent(centipawns: i32) -> f64 {
+        50.0 + 50.0 * (2.0 / (1.0 + (-WIN_PERCENT_SLOPE * centipawns as f64).exp()) - 1.0)
+    }
+}
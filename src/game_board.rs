@@ -1,62 +1,174 @@
+use crate::bitboard::{self, BOARD_SIZE, DARK_SQUARES, SQUARE_COUNT};
+use crate::board_square::BoardSquare;
+use crate::castling_rights::CastlingRights;
 use crate::chess_move::ChessMove;
+use crate::chess960;
+use crate::game_termination::GameTermination;
+use crate::packed_move::{MoveFlag, PackedMove};
 use crate::piece::{Piece, PieceColor, PieceType};
+use crate::polyglot_book::PolyglotBook;
+use crate::position_state::PositionState;
 use crate::square::Square;
+use crate::zobrist_keys::ZobristKeys;
 use anyhow::Context;
+use rand::Rng;
 use std::error::Error;
 use std::fmt::{Display, Formatter};
 
-const BOARD_SIZE: usize = 8;
+const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+const FIFTY_MOVE_RULE_HALFMOVES: u32 = 100;
+const REPETITIONS_FOR_DRAW: usize = 3;
 const KING_FILE: usize = 4;
 const WHITE_KING_RANK: usize = 0;
 const BLACK_KING_RANK: usize = 7;
+const KING_SIDE_ROOK_FILE: usize = 7;
+const QUEEN_SIDE_ROOK_FILE: usize = 0;
+// Castling lands king and rook on the same files in Chess960 as in standard chess
+const KING_SIDE_CASTLE_FILES: (usize, usize) = (6, 5);
+const QUEEN_SIDE_CASTLE_FILES: (usize, usize) = (2, 3);
+const PIECE_TYPE_COUNT: usize = 6;
+const COLOR_COUNT: usize = 2;
 
+const ZOBRIST_SEED: u64 = 0x1995_F1C4_E55B_0A2D;
+static ZOBRIST_KEYS: ZobristKeys = ZobristKeys::from_seed(ZOBRIST_SEED);
+
+const PROMOTION_PIECE_TYPES: [PieceType; 4] = [
+    PieceType::Queen,
+    PieceType::Rook,
+    PieceType::Bishop,
+    PieceType::Knight,
+];
+
+#[derive(Clone)]
 pub struct GameBoard {
-    squares: Vec<Vec<Square>>,
+    // The mailbox answers "what is on this square", the bitboards answer "where are these pieces"
+    mailbox: [Option<Piece>; SQUARE_COUNT],
+    piece_bitboards: [u64; PIECE_TYPE_COUNT],
+    color_bitboards: [u64; COLOR_COUNT],
+    state: PositionState,
+    zobrist_key: u64,
+    position_history: Vec<u64>,
+    is_chess960: bool,
 }
 
 impl GameBoard {
     pub fn new() -> Result<Self, Box<dyn Error>> {
-        let mut squares = Self::get_empty_squares()?;
-
-        squares[0][0].piece = Some(Piece::from(PieceColor::White, PieceType::Rook));
-        squares[0][1].piece = Some(Piece::from(PieceColor::White, PieceType::Knight));
-        squares[0][2].piece = Some(Piece::from(PieceColor::White, PieceType::Bishop));
-        squares[0][3].piece = Some(Piece::from(PieceColor::White, PieceType::Queen));
-        squares[0][4].piece = Some(Piece::from(PieceColor::White, PieceType::King));
-        squares[0][5].piece = Some(Piece::from(PieceColor::White, PieceType::Bishop));
-        squares[0][6].piece = Some(Piece::from(PieceColor::White, PieceType::Knight));
-        squares[0][7].piece = Some(Piece::from(PieceColor::White, PieceType::Rook));
-        squares[1][0].piece = Some(Piece::from(PieceColor::White, PieceType::Pawn));
-        squares[1][1].piece = Some(Piece::from(PieceColor::White, PieceType::Pawn));
-        squares[1][2].piece = Some(Piece::from(PieceColor::White, PieceType::Pawn));
-        squares[1][3].piece = Some(Piece::from(PieceColor::White, PieceType::Pawn));
-        squares[1][4].piece = Some(Piece::from(PieceColor::White, PieceType::Pawn));
-        squares[1][5].piece = Some(Piece::from(PieceColor::White, PieceType::Pawn));
-        squares[1][6].piece = Some(Piece::from(PieceColor::White, PieceType::Pawn));
-        squares[1][7].piece = Some(Piece::from(PieceColor::White, PieceType::Pawn));
-
-        squares[6][0].piece = Some(Piece::from(PieceColor::Black, PieceType::Pawn));
-        squares[6][1].piece = Some(Piece::from(PieceColor::Black, PieceType::Pawn));
-        squares[6][2].piece = Some(Piece::from(PieceColor::Black, PieceType::Pawn));
-        squares[6][3].piece = Some(Piece::from(PieceColor::Black, PieceType::Pawn));
-        squares[6][4].piece = Some(Piece::from(PieceColor::Black, PieceType::Pawn));
-        squares[6][5].piece = Some(Piece::from(PieceColor::Black, PieceType::Pawn));
-        squares[6][6].piece = Some(Piece::from(PieceColor::Black, PieceType::Pawn));
-        squares[6][7].piece = Some(Piece::from(PieceColor::Black, PieceType::Pawn));
-        squares[7][0].piece = Some(Piece::from(PieceColor::Black, PieceType::Rook));
-        squares[7][1].piece = Some(Piece::from(PieceColor::Black, PieceType::Knight));
-        squares[7][2].piece = Some(Piece::from(PieceColor::Black, PieceType::Bishop));
-        squares[7][3].piece = Some(Piece::from(PieceColor::Black, PieceType::Queen));
-        squares[7][4].piece = Some(Piece::from(PieceColor::Black, PieceType::King));
-        squares[7][5].piece = Some(Piece::from(PieceColor::Black, PieceType::Bishop));
-        squares[7][6].piece = Some(Piece::from(PieceColor::Black, PieceType::Knight));
-        squares[7][7].piece = Some(Piece::from(PieceColor::Black, PieceType::Rook));
-
-        Ok(Self { squares })
+        Self::from_fen(STARTING_FEN)
+    }
+
+    pub fn from_fen(fen: &str) -> Result<Self, Box<dyn Error>> {
+        // rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1
+        let mut fields = fen.split_whitespace();
+        let mut board = Self::get_empty_board();
+
+        let placement = fields
+            .next()
+            .context(format!("Unable to find piece placement in FEN: {fen}"))?;
+        let placement_ranks: Vec<&str> = placement.split('/').collect();
+        if placement_ranks.len() != BOARD_SIZE {
+            return Err(Box::from(format!(
+                "Expected {BOARD_SIZE} ranks in FEN placement, found {}: {fen}",
+                placement_ranks.len()
+            )));
+        }
+
+        // FEN lists the ranks from 8 down to 1
+        for (placement_index, placement_rank) in placement_ranks.iter().enumerate() {
+            let rank = BOARD_SIZE - 1 - placement_index;
+            let mut file = 0;
+
+            for c in placement_rank.chars() {
+                if let Some(empty_count) = c.to_digit(10) {
+                    file += empty_count as usize;
+                    continue;
+                }
+
+                if file >= BOARD_SIZE {
+                    return Err(Box::from(format!(
+                        "Too many squares on rank {} in FEN: {fen}",
+                        rank + 1
+                    )));
+                }
+                board.set_piece(
+                    bitboard::square_index(rank, file),
+                    Some(Piece::from_fen_char(c)?),
+                );
+                file += 1;
+            }
+
+            if file != BOARD_SIZE {
+                return Err(Box::from(format!(
+                    "Expected {BOARD_SIZE} squares on rank {}, found {file} in FEN: {fen}",
+                    rank + 1
+                )));
+            }
+        }
+
+        let home_rank_files = |piece_type: PieceType| {
+            [PieceColor::Black, PieceColor::White]
+                .map(|color| board.get_home_rank_files(color, piece_type))
+        };
+        let king_files = home_rank_files(PieceType::King).map(|files| files.first().copied());
+        board.state = PositionState::from_fen_fields(
+            fields,
+            fen,
+            king_files,
+            home_rank_files(PieceType::Rook),
+        )?;
+
+        // Rook file castling rights or a king and rooks off their usual squares can only be Chess960
+        let castling_notation = fen.split_whitespace().nth(2).unwrap_or_default();
+        let castling_rights = board.state.castling_rights;
+        let has_moved_king = [PieceColor::White, PieceColor::Black].iter().any(|color| {
+            (castling_rights.king_side(*color).is_some()
+                || castling_rights.queen_side(*color).is_some())
+                && king_files[*color as usize] != Some(KING_FILE)
+        });
+        board.is_chess960 = !castling_notation.chars().all(|c| "KQkq-".contains(c))
+            || !castling_rights.is_standard()
+            || has_moved_king;
+
+        board.zobrist_key = board.get_zobrist_key(&ZOBRIST_KEYS);
+        board.position_history.push(board.zobrist_key);
+
+        Ok(board)
+    }
+
+    pub fn to_fen(&self) -> Result<String, Box<dyn Error>> {
+        let mut placement_ranks: Vec<String> = Vec::with_capacity(BOARD_SIZE);
+
+        for rank in (0..BOARD_SIZE).rev() {
+            let mut placement_rank = String::new();
+            let mut empty_count = 0;
+
+            for file in 0..BOARD_SIZE {
+                match &self.mailbox[bitboard::square_index(rank, file)] {
+                    None => empty_count += 1,
+                    Some(piece) => {
+                        if empty_count > 0 {
+                            placement_rank.push_str(empty_count.to_string().as_str());
+                            empty_count = 0;
+                        }
+                        placement_rank.push(piece.fen_char());
+                    }
+                }
+            }
+
+            if empty_count > 0 {
+                placement_rank.push_str(empty_count.to_string().as_str());
+            }
+            placement_ranks.push(placement_rank);
+        }
+
+        Ok(format!(
+            "{} {}",
+            placement_ranks.join("/"),
+            self.state.fen_fields(self.is_chess960)?
+        ))
     }
 
     pub fn from(square_info_list: &Vec<String>) -> Result<Self, Box<dyn Error>> {
-        let mut squares = Self::get_empty_squares()?;
+        let mut board = Self::get_empty_board();
 
         // square_info = "piece <color><type> square-<file><rank>"
         for square_info in square_info_list {
@@ -99,169 +211,737 @@ impl GameBoard {
                 .context("Did not find char for rank")?;
             let rank_index = Square::chess_dot_com_index_char_to_index(rank_index_char)?;
 
-            squares[rank_index][file_index].piece = Some(Piece::from(color, piece_type));
+            board.set_piece(
+                bitboard::square_index(rank_index, file_index),
+                Some(Piece::from(color, piece_type)),
+            );
         }
 
-        Ok(Self { squares })
+        // The page has no move history, so assume castling is allowed whenever king and rook are still home
+        board.state.castling_rights = board.get_castling_rights_from_placement();
+        board.zobrist_key = board.get_zobrist_key(&ZOBRIST_KEYS);
+        board.position_history.push(board.zobrist_key);
+
+        Ok(board)
     }
 
-    pub fn set_state(&mut self, new_board: &Self) {
-        for rank in 0..BOARD_SIZE {
-            for file in 0..BOARD_SIZE {
-                self.squares[rank][file].piece = new_board.squares[rank][file].piece.clone();
+    pub fn from_chess960_index(index: usize) -> Result<Self, Box<dyn Error>> {
+        let mut board = Self::from_fen(&chess960::get_starting_fen(index)?)?;
+        // The standard setup is one of the 960 and should still castle king takes rook
+        board.set_chess960(true);
+
+        Ok(board)
+    }
+
+    pub fn is_chess960(&self) -> bool {
+        self.is_chess960
+    }
+
+    pub fn set_chess960(&mut self, is_chess960: bool) {
+        self.is_chess960 = is_chess960;
+    }
+
+    pub fn side_to_move(&self) -> PieceColor {
+        self.state.side_to_move
+    }
+
+    pub fn fullmove_number(&self) -> u32 {
+        self.state.fullmove_number
+    }
+
+    pub fn piece_at(&self, square: &Square) -> Result<Option<&Piece>, Box<dyn Error>> {
+        let square_index = bitboard::square_index(square.rank_index()?, square.file_index()?);
+        Ok(self.mailbox[square_index].as_ref())
+    }
+
+    pub fn legal_moves(&self) -> Result<Vec<ChessMove>, Box<dyn Error>> {
+        Ok(self
+            .legal_packed_moves()?
+            .into_iter()
+            .map(|packed_move| self.to_chess_move(packed_move))
+            .collect())
+    }
+
+    pub fn legal_packed_moves(&self) -> Result<Vec<PackedMove>, Box<dyn Error>> {
+        let color = self.state.side_to_move;
+        let mut legal_moves = Vec::new();
+
+        // A pseudo legal move is legal as long as it does not leave our own king in check
+        for packed_move in self.get_pseudo_legal_moves(color) {
+            let board_after_move = self.get_board_after_move(packed_move)?;
+            if !board_after_move.is_in_check(color) {
+                legal_moves.push(packed_move);
             }
         }
+
+        Ok(legal_moves)
     }
 
-    pub fn get_move_from_difference(
+    pub fn find_legal_move(
         &self,
-        new_board: &GameBoard,
+        chess_move: &ChessMove,
     ) -> Result<Option<ChessMove>, Box<dyn Error>> {
-        let mut changed_positions: Vec<(usize, usize)> = Vec::new();
-        let mut changed_squares: Vec<String> = Vec::new();
+        Ok(self
+            .find_legal_packed_move(chess_move)?
+            .map(|packed_move| self.to_chess_move(packed_move)))
+    }
 
-        for rank in 0..BOARD_SIZE {
-            for file in 0..BOARD_SIZE {
-                if self.squares[rank][file] != new_board.squares[rank][file] {
-                    changed_positions.push((rank, file));
-                    changed_squares.push(self.squares[rank][file].uci_notation());
-                }
+    pub fn to_chess_move(&self, packed_move: PackedMove) -> ChessMove {
+        // End square holds the piece that lands there, which differs from the moving piece on promotion
+        let start_index = packed_move.start().index();
+        let moving_piece = self.mailbox[start_index].clone();
+        let placed_piece = match (packed_move.promotion_type(), &moving_piece) {
+            (Some(promotion_type), Some(piece)) => Some(Piece::from(piece.color, promotion_type)),
+            _ => moving_piece.clone(),
+        };
+
+        // Castles are kept as king takes rook, which standard chess writes as the king's landing square
+        let end_square = match packed_move.flag() == MoveFlag::Castle && !self.is_chess960 {
+            true => {
+                let (king_end_file, _) = Self::get_castle_files(packed_move);
+                BoardSquare::from_index(bitboard::square_index(
+                    packed_move.start().rank_index(),
+                    king_end_file,
+                ))
             }
+            false => packed_move.end(),
+        };
+
+        ChessMove::from(
+            packed_move.start().to_square(moving_piece),
+            end_square.to_square(placed_piece),
+        )
+    }
+
+    pub fn get_castling_rook_square(
+        &self,
+        color: PieceColor,
+        is_king_side: bool,
+    ) -> Option<BoardSquare> {
+        let home_rank = match color {
+            PieceColor::White => WHITE_KING_RANK,
+            PieceColor::Black => BLACK_KING_RANK,
+        };
+        let rook_file = match is_king_side {
+            true => self.state.castling_rights.king_side(color),
+            false => self.state.castling_rights.queen_side(color),
+        }?;
+
+        Some(BoardSquare::from_index(bitboard::square_index(
+            home_rank, rook_file,
+        )))
+    }
+
+    pub fn is_in_check(&self, color: PieceColor) -> bool {
+        self.find_king(color)
+            .is_some_and(|king_square| self.is_square_attacked(king_square, color.opposite()))
+    }
+
+    pub fn apply_move(&mut self, chess_move: &ChessMove) -> Result<(), Box<dyn Error>> {
+        let legal_move = self.find_legal_packed_move(chess_move)?.ok_or_else(|| {
+            format!(
+                "Move {chess_move} is not legal for {} in position {}",
+                self.state.side_to_move,
+                self.to_fen().unwrap_or_default()
+            )
+        })?;
+
+        self.make_move(legal_move)?;
+        self.position_history.push(self.zobrist_key);
+
+        Ok(())
+    }
+
+    fn find_legal_packed_move(
+        &self,
+        chess_move: &ChessMove,
+    ) -> Result<Option<PackedMove>, Box<dyn Error>> {
+        // Squares off the board can never make a legal move
+        let Ok(packed_move) = PackedMove::from_chess_move(chess_move, self) else {
+            return Ok(None);
+        };
+
+        Ok(self
+            .legal_packed_moves()?
+            .into_iter()
+            .find(|legal_move| *legal_move == packed_move))
+    }
+
+    pub fn zobrist_key(&self) -> u64 {
+        self.zobrist_key
+    }
+
+    pub fn get_zobrist_key(&self, keys: &ZobristKeys) -> u64 {
+        // Full recalculation, used to seed the incremental key and for other key sets like Polyglot's
+        let mut key = self.get_state_key(keys);
+
+        for square_index in bitboard::squares(self.get_occupancy()) {
+            if let Some(piece) = &self.mailbox[square_index] {
+                key ^= keys.piece_key(piece, square_index / BOARD_SIZE, square_index % BOARD_SIZE);
+            }
+        }
+
+        key
+    }
+
+    pub fn get_book_move(&self, book: &PolyglotBook) -> Result<Option<ChessMove>, Box<dyn Error>> {
+        // Book moves are picked at random, weighted by how often the book recommends them
+        let mut book_moves = Vec::new();
+        // Polyglot castles as king takes rook, which move matching already understands
+        for entry in book.get_entries(self.get_zobrist_key(book.keys())) {
+            let book_move = ChessMove::from_uci_notation(&entry.uci_notation())?;
+            if let Some(legal_move) = self.find_legal_move(&book_move)? {
+                book_moves.push((legal_move, entry.weight as u32));
+            }
+        }
+
+        let total_weight: u32 = book_moves.iter().map(|(_, weight)| weight).sum();
+        if total_weight == 0 {
+            return Ok(book_moves
+                .into_iter()
+                .next()
+                .map(|(book_move, _)| book_move));
+        }
+
+        let mut choice = rand::rng().random_range(0..total_weight);
+        for (book_move, weight) in book_moves {
+            if choice < weight {
+                return Ok(Some(book_move));
+            }
+            choice -= weight;
+        }
+        Ok(None)
+    }
+
+    pub fn get_termination(&self) -> Result<Option<GameTermination>, Box<dyn Error>> {
+        let color = self.state.side_to_move;
+
+        // No legal moves ends the game before any of the draw rules are considered
+        if self.legal_moves()?.is_empty() {
+            return match self.is_in_check(color) {
+                true => Ok(Some(GameTermination::Checkmate {
+                    winner: color.opposite(),
+                })),
+                false => Ok(Some(GameTermination::Stalemate)),
+            };
+        }
+
+        if self.is_insufficient_material() {
+            return Ok(Some(GameTermination::InsufficientMaterial));
+        }
+
+        if self.state.halfmove_clock >= FIFTY_MOVE_RULE_HALFMOVES {
+            return Ok(Some(GameTermination::FiftyMoveRule));
+        }
+
+        let repetition_count = self
+            .position_history
+            .iter()
+            .filter(|key| **key == self.zobrist_key)
+            .count();
+        if repetition_count >= REPETITIONS_FOR_DRAW {
+            return Ok(Some(GameTermination::ThreefoldRepetition));
+        }
+
+        Ok(None)
+    }
+
+    pub fn perft(&self, depth: u32) -> Result<u64, Box<dyn Error>> {
+        if depth == 0 {
+            return Ok(1);
+        }
+
+        // Counting the last ply's moves saves making every leaf move just to count it
+        let legal_moves = self.legal_packed_moves()?;
+        if depth == 1 {
+            return Ok(legal_moves.len() as u64);
+        }
+
+        let mut node_count = 0;
+        for packed_move in legal_moves {
+            let board_after_move = self.get_board_after_move(packed_move)?;
+            node_count += board_after_move.perft(depth - 1)?;
+        }
+
+        Ok(node_count)
+    }
+
+    pub fn divide(&self, depth: u32) -> Result<Vec<(ChessMove, u64)>, Box<dyn Error>> {
+        // Node counts per root move, for comparing against another engine to find where a count goes wrong.
+        // Moves are written the way that engine expects them, so standard castles are king moves
+        let mut move_counts = Vec::new();
+        for packed_move in self.legal_packed_moves()? {
+            let board_after_move = self.get_board_after_move(packed_move)?;
+            move_counts.push((
+                self.to_chess_move(packed_move),
+                board_after_move.perft(depth.saturating_sub(1))?,
+            ));
+        }
+
+        Ok(move_counts)
+    }
+
+    fn make_move(&mut self, packed_move: PackedMove) -> Result<(), Box<dyn Error>> {
+        let start_square = packed_move.start().index();
+        let end_square = packed_move.end().index();
+        let (start_rank, start_file) = (
+            packed_move.start().rank_index(),
+            packed_move.start().file_index(),
+        );
+        let (end_rank, end_file) = (
+            packed_move.end().rank_index(),
+            packed_move.end().file_index(),
+        );
+
+        let moving_piece = self.mailbox[start_square]
+            .clone()
+            .context(format!("No piece found to move on {}", packed_move.start()))?;
+
+        // Placed piece differs from the moving piece on promotion
+        let placed_piece = match packed_move.promotion_type() {
+            Some(promotion_type) => Piece::from(moving_piece.color, promotion_type),
+            None => moving_piece.clone(),
+        };
+
+        // Castling, en passant and turn keys depend on the whole position so swap them out and back in around the move
+        self.zobrist_key ^= self.get_state_key(&ZOBRIST_KEYS);
+
+        // En passant takes the pawn beside the start square rather than on the end square
+        if packed_move.flag() == MoveFlag::EnPassant {
+            self.set_piece(bitboard::square_index(start_rank, end_file), None);
         }
 
-        println!("CHANGED RANK / FILE : {:?}", changed_positions);
-        println!("CHANGED SQUARES: {:?}", changed_squares);
+        if moving_piece.piece_type == PieceType::King {
+            self.state.castling_rights.remove_all(moving_piece.color);
+        }
+        self.remove_castling_rights_for_rook_square(start_rank, start_file);
+        self.remove_castling_rights_for_rook_square(end_rank, end_file);
+
+        self.state.en_passant_square = match moving_piece.piece_type == PieceType::Pawn
+            && start_rank.abs_diff(end_rank) == 2
+        {
+            true => Some(((start_rank + end_rank) / 2, start_file)),
+            false => None,
+        };
 
-        match changed_positions.len() {
-            0 => Ok(None),
-            2 => self
-                .get_standard_move(&changed_positions, new_board)
-                .map(|chess_move| Some(chess_move)),
-            3 => self
-                .get_en_passant_move(&changed_positions, new_board)
-                .map(|chess_move| Some(chess_move)),
-            4 => self
-                .get_castle_move(&changed_positions, new_board)
-                .map(|chess_move| Some(chess_move)),
-            _ => Err(Box::from(format!(
-                "Error: {} changes made to chess board 😰. Expected 0 or 2 - 4",
-                changed_positions.len()
-            ))),
+        // A castling king "captures" its own rook, so only the other moves can take anything
+        let is_capture =
+            packed_move.flag() != MoveFlag::Castle && self.mailbox[end_square].is_some();
+        self.state
+            .advance_turn(moving_piece.piece_type == PieceType::Pawn || is_capture);
+
+        // Both castling pieces come off before either lands, a Chess960 king can land on the rook's square
+        match packed_move.flag() {
+            MoveFlag::Castle => {
+                let (king_end_file, rook_end_file) = Self::get_castle_files(packed_move);
+                let rook = self.mailbox[end_square].clone();
+                self.set_piece(start_square, None);
+                self.set_piece(end_square, None);
+                self.set_piece(
+                    bitboard::square_index(start_rank, king_end_file),
+                    Some(placed_piece),
+                );
+                self.set_piece(bitboard::square_index(start_rank, rook_end_file), rook);
+            }
+            _ => {
+                self.set_piece(start_square, None);
+                self.set_piece(end_square, Some(placed_piece));
+            }
         }
+        self.zobrist_key ^= self.get_state_key(&ZOBRIST_KEYS);
+
+        Ok(())
     }
 
-    fn get_standard_move(
+    pub fn get_move_from_difference(
         &self,
-        changed_positions: &Vec<(usize, usize)>,
         new_board: &GameBoard,
-    ) -> Result<ChessMove, Box<dyn Error>> {
-        if changed_positions.len() != 2 {
-            return Err(Box::from(format!(
-                "Expected exactly 2 changed positions for standard move. Found: {:?}",
-                changed_positions
-            )));
+    ) -> Result<Option<ChessMove>, Box<dyn Error>> {
+        let changed_squares: Vec<String> = (0..SQUARE_COUNT)
+            .filter(|square_index| self.mailbox[*square_index] != new_board.mailbox[*square_index])
+            .map(|square_index| self.square_at(square_index).uci_notation())
+            .collect();
+
+        if changed_squares.is_empty() {
+            return Ok(None);
         }
 
-        let (rank1, file1) = changed_positions[0];
-        let (rank2, file2) = changed_positions[1];
+        // Counting changed squares cannot tell a capture promotion or a Chess960 castle apart from other moves,
+        // so play every legal move and keep the one that leaves the pieces where the new board has them
+        for packed_move in self.legal_packed_moves()? {
+            let board_after_move = self.get_board_after_move(packed_move)?;
+            if board_after_move.mailbox == new_board.mailbox {
+                return Ok(Some(self.to_chess_move(packed_move)));
+            }
+        }
+
+        Err(Box::from(format!(
+            "No legal move for {} explains the changes made to the chess board 😰: {:?}",
+            self.state.side_to_move, changed_squares
+        )))
+    }
+
+    fn get_board_after_move(&self, packed_move: PackedMove) -> Result<Self, Box<dyn Error>> {
+        // Trying a move out never looks at the history, so leave it behind instead of allocating a copy
+        let mut board = Self {
+            mailbox: self.mailbox.clone(),
+            piece_bitboards: self.piece_bitboards,
+            color_bitboards: self.color_bitboards,
+            state: self.state.clone(),
+            zobrist_key: self.zobrist_key,
+            position_history: Vec::new(),
+            is_chess960: self.is_chess960,
+        };
+        board.make_move(packed_move)?;
 
-        // For any standard move, empty square = starting square, square with piece = ending square
-        let (start_rank, start_file, end_rank, end_file) =
-            if new_board.squares[rank1][file1].piece.is_none() {
-                (rank1, file1, rank2, file2)
-            } else {
-                (rank2, file2, rank1, file1)
+        Ok(board)
+    }
+
+    fn get_pseudo_legal_moves(&self, color: PieceColor) -> Vec<PackedMove> {
+        let mut moves = Vec::new();
+        let own_pieces = self.color_bitboards[color as usize];
+        let occupancy = self.get_occupancy();
+
+        for square_index in bitboard::squares(own_pieces) {
+            let Some(piece) = &self.mailbox[square_index] else {
+                continue;
             };
 
-        let start_square = self.squares[start_rank][start_file].clone();
-        let end_square = new_board.squares[end_rank][end_file].clone();
+            let targets = match piece.piece_type {
+                PieceType::Pawn => {
+                    self.add_pawn_moves(square_index, color, &mut moves);
+                    continue;
+                }
+                PieceType::Knight => bitboard::knight_attacks(square_index),
+                PieceType::Bishop => bitboard::bishop_attacks(square_index, occupancy),
+                PieceType::Rook => bitboard::rook_attacks(square_index, occupancy),
+                PieceType::Queen => {
+                    bitboard::bishop_attacks(square_index, occupancy)
+                        | bitboard::rook_attacks(square_index, occupancy)
+                }
+                PieceType::King => {
+                    self.add_castle_moves(square_index, color, &mut moves);
+                    bitboard::king_attacks(square_index)
+                }
+            };
+
+            for target_index in bitboard::squares(targets & !own_pieces) {
+                moves.push(Self::create_move(
+                    square_index,
+                    target_index,
+                    MoveFlag::Normal,
+                ));
+            }
+        }
 
-        Ok(ChessMove::from(start_square, end_square))
+        moves
     }
 
-    fn get_en_passant_move(
-        &self,
-        changed_positions: &Vec<(usize, usize)>,
-        new_board: &GameBoard,
-    ) -> Result<ChessMove, Box<dyn Error>> {
-        if changed_positions.len() != 3 {
-            return Err(Box::from(format!(
-                "Expected exactly 3 changed positions for en passant move. Found: {:?}",
-                changed_positions
-            )));
+    fn add_pawn_moves(&self, square_index: usize, color: PieceColor, moves: &mut Vec<PackedMove>) {
+        let (one_step_index, two_step_index, starting_rank) = match color {
+            PieceColor::White => (square_index + BOARD_SIZE, square_index + 2 * BOARD_SIZE, 1),
+            PieceColor::Black => (
+                square_index.wrapping_sub(BOARD_SIZE),
+                square_index.wrapping_sub(2 * BOARD_SIZE),
+                6,
+            ),
+        };
+        let occupancy = self.get_occupancy();
+
+        // Only a hand written FEN can leave a pawn on the last rank, it has nowhere to go
+        if one_step_index >= SQUARE_COUNT {
+            return;
         }
 
-        // Pawn ending square is the only square with a piece on it
-        let (end_rank, end_file) = changed_positions
-            .iter()
-            .find(|(rank, file)| new_board.squares[*rank][*file].piece.is_some())
-            .context(format!(
-                "Could not find pawn ending position for en passant move with {:?}",
-                changed_positions
-            ))?
-            .clone();
-
-        // Pawn starting square is the only square on a different file from end position
-        let (start_rank, start_file) = changed_positions
-            .iter()
-            .find(|(_rank, file)| *file != end_file)
-            .context(format!(
-                "Could not find pawn starting position for en passant move with {:?}",
-                changed_positions
-            ))?
-            .clone();
+        if occupancy & bitboard::square_bit(one_step_index) == 0 {
+            Self::add_pawn_move(square_index, one_step_index, MoveFlag::Normal, moves);
 
-        let start_square = self.squares[start_rank][start_file].clone();
-        let end_square = new_board.squares[end_rank][end_file].clone();
+            if square_index / BOARD_SIZE == starting_rank
+                && occupancy & bitboard::square_bit(two_step_index) == 0
+            {
+                Self::add_pawn_move(square_index, two_step_index, MoveFlag::Normal, moves);
+            }
+        }
 
-        Ok(ChessMove::from(start_square, end_square))
+        let pawn_attacks = bitboard::pawn_attacks(color, square_index);
+        let enemy_pieces = self.color_bitboards[color.opposite() as usize];
+        for target_index in bitboard::squares(pawn_attacks & enemy_pieces) {
+            Self::add_pawn_move(square_index, target_index, MoveFlag::Normal, moves);
+        }
+
+        if let Some((rank, file)) = self.state.en_passant_square {
+            let en_passant_index = bitboard::square_index(rank, file);
+            if pawn_attacks & bitboard::square_bit(en_passant_index) != 0 {
+                moves.push(Self::create_move(
+                    square_index,
+                    en_passant_index,
+                    MoveFlag::EnPassant,
+                ));
+            }
+        }
     }
 
-    fn get_castle_move(
-        &self,
-        changed_positions: &Vec<(usize, usize)>,
-        new_board: &GameBoard,
-    ) -> Result<ChessMove, Box<dyn Error>> {
-        if changed_positions.len() != 4 {
-            return Err(Box::from(format!(
-                "Expected exactly 4 changed positions for castle move. Found: {:?}",
-                changed_positions
-            )));
+    fn add_pawn_move(
+        start_index: usize,
+        end_index: usize,
+        flag: MoveFlag,
+        moves: &mut Vec<PackedMove>,
+    ) {
+        let end_rank = end_index / BOARD_SIZE;
+        if end_rank != WHITE_KING_RANK && end_rank != BLACK_KING_RANK {
+            moves.push(Self::create_move(start_index, end_index, flag));
+            return;
         }
 
-        // Castle always starts on the king file, just need to find which rank
-        let start_rank = if changed_positions.contains(&(WHITE_KING_RANK, KING_FILE)) {
-            WHITE_KING_RANK
-        } else {
-            BLACK_KING_RANK
+        for piece_type in PROMOTION_PIECE_TYPES {
+            moves.push(PackedMove::from(
+                BoardSquare::from_index(start_index),
+                BoardSquare::from_index(end_index),
+                Some(piece_type),
+                MoveFlag::Promotion,
+            ));
+        }
+    }
+
+    fn add_castle_moves(
+        &self,
+        square_index: usize,
+        color: PieceColor,
+        moves: &mut Vec<PackedMove>,
+    ) {
+        let home_rank = match color {
+            PieceColor::White => WHITE_KING_RANK,
+            PieceColor::Black => BLACK_KING_RANK,
         };
+        let enemy_color = color.opposite();
+
+        if square_index / BOARD_SIZE != home_rank
+            || self.is_square_attacked(square_index, enemy_color)
+        {
+            return;
+        }
+
+        let king_file = square_index % BOARD_SIZE;
+        let rook = Some(Piece::from(color, PieceType::Rook));
+        let castling_rights = self.state.castling_rights;
+        let files_between = |from: usize, to: usize| from.min(to)..=from.max(to);
+
+        for rook_file in [
+            castling_rights.king_side(color),
+            castling_rights.queen_side(color),
+        ]
+        .into_iter()
+        .flatten()
+        {
+            let rook_index = bitboard::square_index(home_rank, rook_file);
+            if self.mailbox[rook_index] != rook {
+                continue;
+            }
+            let castle_move = Self::create_move(square_index, rook_index, MoveFlag::Castle);
+            let (king_end_file, rook_end_file) = Self::get_castle_files(castle_move);
+
+            // Both paths may only hold the castling king and rook, and the king may not pass through or land on an attacked square
+            let is_path_empty = files_between(king_file, king_end_file)
+                .chain(files_between(rook_file, rook_end_file))
+                .all(|file| {
+                    file == king_file
+                        || file == rook_file
+                        || self.mailbox[bitboard::square_index(home_rank, file)].is_none()
+                });
+            let is_king_path_safe = files_between(king_file, king_end_file).all(|file| {
+                !self.is_square_attacked(bitboard::square_index(home_rank, file), enemy_color)
+            });
+
+            if is_path_empty && is_king_path_safe {
+                moves.push(castle_move);
+            }
+        }
+    }
+
+    fn get_castle_files(castle_move: PackedMove) -> (usize, usize) {
+        // King and rook landing files, castles are stored as the king taking its own rook
+        match castle_move.end().file_index() > castle_move.start().file_index() {
+            true => KING_SIDE_CASTLE_FILES,
+            false => QUEEN_SIDE_CASTLE_FILES,
+        }
+    }
 
-        // If king side castle, end file > king file, else is a queen side castle with end file < king file
-        let end_file = if changed_positions.contains(&(start_rank, KING_FILE + 2)) {
-            KING_FILE + 2
-        } else {
-            KING_FILE - 2
+    fn create_move(start_index: usize, end_index: usize, flag: MoveFlag) -> PackedMove {
+        PackedMove::from(
+            BoardSquare::from_index(start_index),
+            BoardSquare::from_index(end_index),
+            None,
+            flag,
+        )
+    }
+
+    fn is_square_attacked(&self, square_index: usize, attacker_color: PieceColor) -> bool {
+        let occupancy = self.get_occupancy();
+        let attackers = |piece_type: PieceType| self.get_pieces(attacker_color, piece_type);
+        let diagonal_attackers = attackers(PieceType::Bishop) | attackers(PieceType::Queen);
+        let straight_attackers = attackers(PieceType::Rook) | attackers(PieceType::Queen);
+
+        // Attacking pawns sit where a defending pawn on this square would capture
+        bitboard::pawn_attacks(attacker_color.opposite(), square_index) & attackers(PieceType::Pawn)
+            != 0
+            || bitboard::knight_attacks(square_index) & attackers(PieceType::Knight) != 0
+            || bitboard::king_attacks(square_index) & attackers(PieceType::King) != 0
+            || bitboard::bishop_attacks(square_index, occupancy) & diagonal_attackers != 0
+            || bitboard::rook_attacks(square_index, occupancy) & straight_attackers != 0
+    }
+
+    fn get_home_rank_files(&self, color: PieceColor, piece_type: PieceType) -> Vec<usize> {
+        let home_rank = match color {
+            PieceColor::White => WHITE_KING_RANK,
+            PieceColor::Black => BLACK_KING_RANK,
         };
 
-        let start_square = self.squares[start_rank][KING_FILE].clone();
-        let end_square = new_board.squares[start_rank][end_file].clone();
+        bitboard::squares(self.get_pieces(color, piece_type))
+            .filter(|square_index| square_index / BOARD_SIZE == home_rank)
+            .map(|square_index| square_index % BOARD_SIZE)
+            .collect()
+    }
 
-        Ok(ChessMove::from(start_square, end_square))
+    fn find_king(&self, color: PieceColor) -> Option<usize> {
+        bitboard::squares(self.get_pieces(color, PieceType::King)).next()
     }
 
-    fn get_empty_squares() -> Result<Vec<Vec<Square>>, Box<dyn Error>> {
-        let mut squares: Vec<Vec<Square>> = Vec::with_capacity(BOARD_SIZE);
+    fn get_pieces(&self, color: PieceColor, piece_type: PieceType) -> u64 {
+        self.color_bitboards[color as usize] & self.piece_bitboards[piece_type as usize]
+    }
 
-        for rank_index in 0..BOARD_SIZE {
-            squares.push(Vec::with_capacity(BOARD_SIZE));
-            for file_index in 0..BOARD_SIZE {
-                let rank = Square::index_to_rank(rank_index)?;
-                let file = Square::index_to_file(file_index)?;
-                squares[rank_index].push(Square::from(rank, file, None));
+    fn get_occupancy(&self) -> u64 {
+        self.color_bitboards[PieceColor::White as usize]
+            | self.color_bitboards[PieceColor::Black as usize]
+    }
+
+    fn square_at(&self, square_index: usize) -> Square {
+        Square::from_indexes(
+            square_index / BOARD_SIZE,
+            square_index % BOARD_SIZE,
+            self.mailbox[square_index].clone(),
+        )
+    }
+
+    fn remove_castling_rights_for_rook_square(&mut self, rank: usize, file: usize) {
+        let color = match rank {
+            WHITE_KING_RANK => PieceColor::White,
+            BLACK_KING_RANK => PieceColor::Black,
+            _ => return,
+        };
+
+        self.state.castling_rights.remove_for_rook_file(color, file);
+    }
+
+    fn get_castling_rights_from_placement(&self) -> CastlingRights {
+        let mut castling_rights = CastlingRights::all();
+
+        for (color, home_rank) in [
+            (PieceColor::White, WHITE_KING_RANK),
+            (PieceColor::Black, BLACK_KING_RANK),
+        ] {
+            let piece_on_file =
+                |file: usize| &self.mailbox[bitboard::square_index(home_rank, file)];
+            let rook = Some(Piece::from(color, PieceType::Rook));
+
+            if *piece_on_file(KING_FILE) != Some(Piece::from(color, PieceType::King)) {
+                castling_rights.remove_all(color);
+            }
+            if *piece_on_file(KING_SIDE_ROOK_FILE) != rook {
+                castling_rights.remove_king_side(color);
+            }
+            if *piece_on_file(QUEEN_SIDE_ROOK_FILE) != rook {
+                castling_rights.remove_queen_side(color);
             }
         }
 
-        Ok(squares)
+        castling_rights
+    }
+
+    fn is_insufficient_material(&self) -> bool {
+        let mating_material = self.piece_bitboards[PieceType::Pawn as usize]
+            | self.piece_bitboards[PieceType::Rook as usize]
+            | self.piece_bitboards[PieceType::Queen as usize];
+        if mating_material != 0 {
+            return false;
+        }
+
+        let bishops = self.piece_bitboards[PieceType::Bishop as usize];
+        let minor_pieces = self.piece_bitboards[PieceType::Knight as usize] | bishops;
+
+        // A lone minor piece can never mate, neither can any number of bishops all on one square color
+        minor_pieces.count_ones() <= 1
+            || (minor_pieces == bishops
+                && (bishops & DARK_SQUARES == 0 || bishops & !DARK_SQUARES == 0))
+    }
+
+    fn get_state_key(&self, keys: &ZobristKeys) -> u64 {
+        let mut key = 0;
+
+        let castling_rights = self.state.castling_rights;
+        let castling_flags = [
+            castling_rights.white_king_side,
+            castling_rights.white_queen_side,
+            castling_rights.black_king_side,
+            castling_rights.black_queen_side,
+        ];
+        for (castling_index, _) in castling_flags
+            .iter()
+            .enumerate()
+            .filter(|(_, rook_file)| rook_file.is_some())
+        {
+            key ^= keys.castling_key(castling_index);
+        }
+
+        if let Some((_, file)) = self.get_capturable_en_passant_square() {
+            key ^= keys.en_passant_key(file);
+        }
+        if self.state.side_to_move == PieceColor::White {
+            key ^= keys.turn_key();
+        }
+
+        key
+    }
+
+    fn set_piece(&mut self, square_index: usize, piece: Option<Piece>) {
+        // Every piece change on a live board goes through here to keep the bitboards and zobrist key in step
+        let square_bit = bitboard::square_bit(square_index);
+        let (rank, file) = (square_index / BOARD_SIZE, square_index % BOARD_SIZE);
+
+        if let Some(old_piece) = &self.mailbox[square_index] {
+            self.piece_bitboards[old_piece.piece_type as usize] &= !square_bit;
+            self.color_bitboards[old_piece.color as usize] &= !square_bit;
+            self.zobrist_key ^= ZOBRIST_KEYS.piece_key(old_piece, rank, file);
+        }
+        if let Some(new_piece) = &piece {
+            self.piece_bitboards[new_piece.piece_type as usize] |= square_bit;
+            self.color_bitboards[new_piece.color as usize] |= square_bit;
+            self.zobrist_key ^= ZOBRIST_KEYS.piece_key(new_piece, rank, file);
+        }
+        self.mailbox[square_index] = piece;
+    }
+
+    fn get_capturable_en_passant_square(&self) -> Option<(usize, usize)> {
+        // En passant only makes positions different when a pawn can actually take it
+        let color = self.state.side_to_move;
+        self.state.en_passant_square.filter(|(rank, file)| {
+            let en_passant_index = bitboard::square_index(*rank, *file);
+            bitboard::pawn_attacks(color.opposite(), en_passant_index)
+                & self.get_pieces(color, PieceType::Pawn)
+                != 0
+        })
+    }
+
+    fn get_empty_board() -> Self {
+        Self {
+            mailbox: std::array::from_fn(|_| None),
+            piece_bitboards: [0; PIECE_TYPE_COUNT],
+            color_bitboards: [0; COLOR_COUNT],
+            state: PositionState::new(),
+            zobrist_key: 0,
+            position_history: Vec::new(),
+            is_chess960: false,
+        }
     }
 }
 
@@ -271,8 +951,7 @@ impl Display for GameBoard {
 
         for rank_index in 0..BOARD_SIZE {
             for file_index in 0..BOARD_SIZE {
-                let square = &self.squares[rank_index][file_index];
-                output += match &square.piece {
+                output += match &self.mailbox[bitboard::square_index(rank_index, file_index)] {
                     None => String::from("   "),
                     Some(piece) => format!("{}{} ", piece.color, piece.piece_type),
                 }
@@ -284,3 +963,244 @@ impl Display for GameBoard {
         write!(f, "{}", output)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Reference counts from https://www.chessprogramming.org/Perft_Results
+    const KIWIPETE_FEN: &str =
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+    const POSITION_3_FEN: &str = "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1";
+    const POSITION_4_FEN: &str = "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1";
+    const POSITION_4_MIRRORED_FEN: &str =
+        "r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1";
+    const POSITION_5_FEN: &str = "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8";
+    const POSITION_6_FEN: &str =
+        "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10";
+
+    // Reference counts from https://www.chessprogramming.org/Chess960_Perft_Results
+    const CHESS960_POSITION_1_FEN: &str =
+        "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9";
+    const CHESS960_POSITION_2_FEN: &str =
+        "2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9";
+
+    fn assert_perft(fen: &str, expected_node_counts: &[u64]) {
+        let board = GameBoard::from_fen(fen).unwrap();
+        for (depth_index, expected_node_count) in expected_node_counts.iter().enumerate() {
+            let depth = depth_index as u32 + 1;
+            assert_eq!(
+                board.perft(depth).unwrap(),
+                *expected_node_count,
+                "perft({depth}) of {fen}"
+            );
+        }
+    }
+
+    fn count_moves(fen: &str, flag: MoveFlag) -> usize {
+        let board = GameBoard::from_fen(fen).unwrap();
+        board
+            .legal_packed_moves()
+            .unwrap()
+            .into_iter()
+            .filter(|packed_move| packed_move.flag() == flag)
+            .count()
+    }
+
+    #[test]
+    fn perft_starting_position() {
+        assert_perft(STARTING_FEN, &[20, 400, 8_902, 197_281]);
+    }
+
+    #[test]
+    fn perft_kiwipete() {
+        assert_perft(KIWIPETE_FEN, &[48, 2_039, 97_862]);
+    }
+
+    #[test]
+    fn perft_position_3_en_passant_and_pins() {
+        assert_perft(POSITION_3_FEN, &[14, 191, 2_812, 43_238]);
+    }
+
+    #[test]
+    fn perft_position_4_promotions_and_castling() {
+        assert_perft(POSITION_4_FEN, &[6, 264, 9_467]);
+        assert_perft(POSITION_4_MIRRORED_FEN, &[6, 264, 9_467]);
+    }
+
+    #[test]
+    fn perft_position_5() {
+        assert_perft(POSITION_5_FEN, &[44, 1_486, 62_379]);
+    }
+
+    #[test]
+    fn perft_position_6() {
+        assert_perft(POSITION_6_FEN, &[46, 2_079, 89_890]);
+    }
+
+    #[test]
+    fn perft_chess960_positions() {
+        assert_perft(CHESS960_POSITION_1_FEN, &[21, 528, 12_189, 326_672]);
+        assert_perft(CHESS960_POSITION_2_FEN, &[21, 807, 18_002]);
+    }
+
+    #[test]
+    fn chess960_index_518_is_the_standard_setup() {
+        let board = GameBoard::from_chess960_index(518).unwrap();
+
+        assert!(board.is_chess960());
+        assert_eq!(
+            board.to_fen().unwrap(),
+            STARTING_FEN.replace("KQkq", "HAha")
+        );
+        assert_eq!(board.perft(3).unwrap(), 8_902);
+        assert!(GameBoard::from_chess960_index(960).is_err());
+    }
+
+    #[test]
+    fn chess960_castles_are_written_as_king_takes_rook() {
+        // King side lands on g1 and queen side on c1 wherever the king and rooks started
+        let cases = [
+            (
+                "4k3/8/8/8/8/8/8/1R3K1R w HB - 0 1",
+                "O-O",
+                "f1h1",
+                "4k3/8/8/8/8/8/8/1R3RK1 b - - 1 1",
+            ),
+            (
+                "4k3/8/8/8/8/8/8/1R3K1R w HB - 0 1",
+                "O-O-O",
+                "f1b1",
+                "4k3/8/8/8/8/8/8/2KR3R b - - 1 1",
+            ),
+            (
+                "4k3/8/8/8/8/8/8/6KR w H - 0 1",
+                "O-O",
+                "g1h1",
+                "4k3/8/8/8/8/8/8/5RK1 b - - 1 1",
+            ),
+        ];
+
+        for (fen, san_notation, uci_notation, fen_after_castle) in cases {
+            let mut board = GameBoard::from_fen(fen).unwrap();
+            let castle_move = ChessMove::from_san_notation(san_notation, &board).unwrap();
+
+            assert_eq!(castle_move.uci_notation(), uci_notation);
+            assert_eq!(castle_move.san_notation(&board).unwrap(), san_notation);
+            board.apply_move(&castle_move).unwrap();
+            assert_eq!(board.to_fen().unwrap(), fen_after_castle);
+        }
+    }
+
+    #[test]
+    fn divide_adds_up_to_perft() {
+        let board = GameBoard::from_fen(KIWIPETE_FEN).unwrap();
+        let move_counts = board.divide(2).unwrap();
+
+        assert_eq!(move_counts.len(), 48);
+        assert_eq!(
+            move_counts.iter().map(|(_, count)| count).sum::<u64>(),
+            board.perft(2).unwrap()
+        );
+    }
+
+    #[test]
+    fn divide_writes_castles_as_uci_moves() {
+        let root_moves = |board: &GameBoard| -> Vec<String> {
+            board
+                .divide(1)
+                .unwrap()
+                .iter()
+                .map(|(chess_move, _)| chess_move.uci_notation())
+                .collect()
+        };
+
+        // Standard castles are the king's two square move, Chess960 keeps king takes rook
+        let mut board = GameBoard::from_fen(KIWIPETE_FEN).unwrap();
+        let standard_moves = root_moves(&board);
+        assert!(standard_moves.contains(&String::from("e1g1")));
+        assert!(standard_moves.contains(&String::from("e1c1")));
+        assert!(!standard_moves.contains(&String::from("e1h1")));
+        assert!(!standard_moves.contains(&String::from("e1a1")));
+
+        board.set_chess960(true);
+        let chess960_moves = root_moves(&board);
+        assert!(chess960_moves.contains(&String::from("e1h1")));
+        assert!(chess960_moves.contains(&String::from("e1a1")));
+        assert!(!chess960_moves.contains(&String::from("e1g1")));
+        assert!(!chess960_moves.contains(&String::from("e1c1")));
+    }
+
+    #[test]
+    fn castling_is_blocked_through_check() {
+        // Bishop on a3 covers f8 so black can only castle queen side
+        assert_eq!(
+            count_moves("r3k2r/8/8/8/8/B7/8/4K3 b kq - 0 1", MoveFlag::Castle),
+            1
+        );
+        assert_eq!(count_moves(KIWIPETE_FEN, MoveFlag::Castle), 2);
+        assert_eq!(
+            count_moves("r3k2r/8/8/8/8/8/8/R3K2R w - - 0 1", MoveFlag::Castle),
+            0
+        );
+    }
+
+    #[test]
+    fn en_passant_is_not_allowed_when_it_exposes_the_king() {
+        // Taking on e3 would clear the rank between the king on a4 and the rook on h4
+        assert_eq!(
+            count_moves("8/8/8/8/k2Pp2R/8/8/4K3 b - d3 0 1", MoveFlag::EnPassant),
+            0
+        );
+        assert_eq!(
+            count_moves("8/8/8/8/k2Pp3/8/8/4K2R b - d3 0 1", MoveFlag::EnPassant),
+            1
+        );
+    }
+
+    fn assert_moves_found_from_difference(fen: &str) {
+        let board = GameBoard::from_fen(fen).unwrap();
+        for legal_move in board.legal_moves().unwrap() {
+            let mut new_board = board.clone();
+            new_board.apply_move(&legal_move).unwrap();
+            let found_move = board.get_move_from_difference(&new_board).unwrap().unwrap();
+            assert_eq!(
+                found_move.uci_notation(),
+                legal_move.uci_notation(),
+                "{fen}"
+            );
+        }
+    }
+
+    #[test]
+    fn move_from_difference_finds_every_legal_move() {
+        // Promotions to every piece type with and without a capture, en passant and both castles
+        assert_moves_found_from_difference("1n1n4/2P5/8/8/8/8/8/k3K3 w - - 0 1");
+        assert_moves_found_from_difference("4k3/8/8/8/8/8/1p6/R1N1K3 b - - 0 1");
+        assert_moves_found_from_difference("8/8/8/8/k2Pp3/8/8/4K2R b - d3 0 1");
+        assert_moves_found_from_difference(KIWIPETE_FEN);
+        assert_moves_found_from_difference(POSITION_4_FEN);
+        assert_moves_found_from_difference(CHESS960_POSITION_1_FEN);
+    }
+
+    #[test]
+    fn move_from_difference_rejects_impossible_boards() {
+        let board = GameBoard::new().unwrap();
+        assert!(board.get_move_from_difference(&board).unwrap().is_none());
+
+        // A white queen appearing on e4 is not something any legal move does
+        let new_board =
+            GameBoard::from_fen("rnbqkbnr/pppppppp/8/8/4Q3/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1")
+                .unwrap();
+        assert!(board.get_move_from_difference(&new_board).is_err());
+    }
+
+    #[test]
+    fn promotions_include_every_piece_type() {
+        // Straight push and both captures, each to queen, rook, bishop and knight
+        assert_eq!(
+            count_moves("1n1n4/2P5/8/8/8/8/8/k3K3 w - - 0 1", MoveFlag::Promotion),
+            12
+        );
+    }
+}
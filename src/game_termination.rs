@@ -0,0 +1,43 @@
+use crate::piece::PieceColor;
+use std::fmt::{Display, Formatter};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum GameTermination {
+    Checkmate { winner: PieceColor },
+    Stalemate,
+    ThreefoldRepetition,
+    FiftyMoveRule,
+    InsufficientMaterial,
+}
+
+impl GameTermination {
+    pub fn pgn_result(&self) -> &'static str {
+        match self {
+            GameTermination::Checkmate {
+                winner: PieceColor::White,
+            } => "1-0",
+            GameTermination::Checkmate {
+                winner: PieceColor::Black,
+            } => "0-1",
+            _ => "1/2-1/2",
+        }
+    }
+}
+
+impl Display for GameTermination {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let display_str = match self {
+            GameTermination::Checkmate {
+                winner: PieceColor::White,
+            } => "White wins by checkmate",
+            GameTermination::Checkmate {
+                winner: PieceColor::Black,
+            } => "Black wins by checkmate",
+            GameTermination::Stalemate => "Draw by stalemate",
+            GameTermination::ThreefoldRepetition => "Draw by threefold repetition",
+            GameTermination::FiftyMoveRule => "Draw by the fifty move rule",
+            GameTermination::InsufficientMaterial => "Draw by insufficient material",
+        };
+        write!(f, "{}", display_str)
+    }
+}
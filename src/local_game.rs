@@ -0,0 +1,253 @@
+use crate::board_renderer::BoardRenderer;
+use crate::chess_move::ChessMove;
+use crate::engine_config::EngineConfig;
+use crate::game_analyzer::GameAnalyzer;
+use crate::game_board::GameBoard;
+use crate::pgn::PgnGame;
+use crate::piece::PieceColor;
+use crate::polyglot_book::PolyglotBook;
+use crate::search_limits::SearchLimits;
+use crate::uci_engine::UciEngine;
+use std::error::Error;
+use std::io::Write;
+use tokio::io::{AsyncBufReadExt, BufReader, Lines, Stdin, stdin};
+
+const HUMAN_NAME: &str = "Trainee";
+const RESIGN_COMMANDS: [&str; 2] = ["resign", "quit"];
+
+pub struct LocalGame {
+    engine: UciEngine,
+    search_limits: SearchLimits,
+    candidate_count: u32,
+    human_color: PieceColor,
+    renderer: BoardRenderer,
+    analyzer: Option<GameAnalyzer>,
+    book: Option<PolyglotBook>,
+    starting_board: GameBoard,
+}
+
+impl LocalGame {
+    pub async fn new(
+        engine_config: &EngineConfig,
+        search_limits: SearchLimits,
+        candidate_count: u32,
+        engine_options: &[(String, String)],
+        ponder: bool,
+        human_color: PieceColor,
+        analyzer: Option<GameAnalyzer>,
+    ) -> Result<LocalGame, Box<dyn Error>> {
+        search_limits.ensure_finite()?;
+
+        let mut engine = UciEngine::new(engine_config).await?;
+        println!("Playing against {} by {}", engine.name(), engine.author());
+        for (name, value) in engine_options {
+            engine.set_option(name, value).await?;
+        }
+        if ponder {
+            engine.enable_ponder().await?;
+        }
+
+        Ok(LocalGame {
+            engine,
+            search_limits,
+            candidate_count,
+            human_color,
+            renderer: BoardRenderer::from(human_color == PieceColor::Black),
+            analyzer,
+            book: None,
+            starting_board: GameBoard::new()?,
+        })
+    }
+
+    pub fn set_book(&mut self, book: PolyglotBook) {
+        self.book = Some(book);
+    }
+
+    pub fn set_starting_board(&mut self, starting_board: GameBoard) {
+        self.starting_board = starting_board;
+    }
+
+    pub async fn play(&mut self) -> Result<(), Box<dyn Error>> {
+        let mut board = self.starting_board.clone();
+        let mut pgn_game = PgnGame::new();
+        pgn_game.set_starting_board_tags(&board)?;
+        let mut input = BufReader::new(stdin()).lines();
+        self.engine.reset(&board).await?;
+
+        println!(
+            "Enter moves in UCI (e2e4) or SAN (Nf3), 'moves' to list legal moves or 'resign' to give up"
+        );
+        println!("{}", self.renderer.render(&board, None)?);
+
+        loop {
+            if let Some(termination) = board.get_termination()? {
+                println!("GAME OVER: {termination}");
+                pgn_game.result = termination.pgn_result().to_string();
+                break;
+            }
+
+            if board.side_to_move() != self.human_color {
+                let engine_move = self.play_engine_move(&mut board, &mut pgn_game).await?;
+                println!("{}", self.renderer.render(&board, Some(&engine_move))?);
+                continue;
+            }
+
+            let Some(human_move) = Self::read_human_move(&board, &mut input).await? else {
+                println!("GAME OVER: {} resigned", HUMAN_NAME);
+                pgn_game.result = match self.human_color {
+                    PieceColor::White => String::from("0-1"),
+                    PieceColor::Black => String::from("1-0"),
+                };
+                break;
+            };
+
+            self.engine.record_opponent_move(&human_move).await?;
+
+            board.apply_move(&human_move)?;
+            println!("{}", self.renderer.render(&board, Some(&human_move))?);
+            pgn_game.push_move(human_move, None);
+        }
+
+        self.engine.abandon_search().await;
+        self.archive_game(&mut pgn_game)?;
+
+        if let Some(analyzer) = &mut self.analyzer {
+            println!("{}", analyzer.analyze(&pgn_game).await?);
+        }
+        Ok(())
+    }
+
+    async fn play_engine_move(
+        &mut self,
+        board: &mut GameBoard,
+        pgn_game: &mut PgnGame,
+    ) -> Result<ChessMove, Box<dyn Error>> {
+        // Book moves are played instantly until the game leaves the book
+        let book_move = match &self.book {
+            Some(book) => board.get_book_move(book)?,
+            None => None,
+        };
+        if let Some(book_move) = book_move {
+            self.engine.abandon_search().await;
+            println!(
+                "BOOK MOVE: {} ({book_move})",
+                book_move.san_notation(board)?
+            );
+            board.apply_move(&book_move)?;
+            self.engine.record_move_played(&book_move);
+            pgn_game.push_move(book_move.clone(), Some(String::from("book")));
+            return Ok(book_move);
+        }
+
+        self.engine
+            .search_unless_pondered(&self.search_limits, self.candidate_count)
+            .await?;
+        let search_result = self.engine.wait_for_search().await?;
+
+        if self.candidate_count > 1 {
+            for (index, candidate) in search_result.candidates.iter().enumerate() {
+                println!("CANDIDATE {}: {candidate}", index + 1);
+            }
+        }
+
+        let best_move = search_result.best_move;
+        let best_move = board
+            .find_legal_move(&best_move)?
+            .ok_or_else(|| format!("Engine move {best_move} is not a legal move"))?;
+        println!(
+            "ENGINE MOVE: {} ({best_move})",
+            best_move.san_notation(board)?
+        );
+        println!("ENGINE EVAL: {}", search_result.info);
+        let evaluation_comment = search_result.info.score.map(|score| {
+            format!(
+                "{}/{}",
+                score.for_white(board.side_to_move()),
+                search_result.info.depth.unwrap_or_default()
+            )
+        });
+
+        board.apply_move(&best_move)?;
+        self.engine.record_move_played(&best_move);
+        pgn_game.push_move(best_move.clone(), evaluation_comment);
+
+        if board.get_termination()?.is_some() {
+            return Ok(best_move);
+        }
+
+        self.engine
+            .ponder_expected_reply(
+                board,
+                search_result.ponder_move.as_ref(),
+                &self.search_limits,
+                self.candidate_count,
+            )
+            .await?;
+
+        Ok(best_move)
+    }
+
+    async fn read_human_move(
+        board: &GameBoard,
+        input: &mut Lines<BufReader<Stdin>>,
+    ) -> Result<Option<ChessMove>, Box<dyn Error>> {
+        loop {
+            print!("YOUR MOVE: ");
+            std::io::stdout().flush()?;
+
+            // Running out of input counts as walking away from the board
+            let Some(line) = input.next_line().await? else {
+                return Ok(None);
+            };
+            let notation = line.trim();
+
+            if RESIGN_COMMANDS.contains(&notation) {
+                return Ok(None);
+            }
+            if notation == "moves" {
+                let legal_moves = board
+                    .legal_moves()?
+                    .iter()
+                    .map(|chess_move| chess_move.san_notation(board))
+                    .collect::<Result<Vec<String>, Box<dyn Error>>>()?;
+                println!("LEGAL MOVES: {}", legal_moves.join(" "));
+                continue;
+            }
+
+            match Self::parse_human_move(notation, board) {
+                Ok(chess_move) => return Ok(Some(chess_move)),
+                Err(error) => println!("Illegal or unreadable move '{notation}': {error}"),
+            }
+        }
+    }
+
+    fn parse_human_move(notation: &str, board: &GameBoard) -> Result<ChessMove, Box<dyn Error>> {
+        // Try UCI first and fall back to SAN, which also reports why a move is not legal
+        let uci_move = ChessMove::from_uci_notation(notation)
+            .ok()
+            .and_then(|chess_move| board.find_legal_move(&chess_move).ok().flatten());
+
+        match uci_move {
+            Some(chess_move) => Ok(chess_move),
+            None => ChessMove::from_san_notation(notation, board),
+        }
+    }
+
+    fn archive_game(&self, pgn_game: &mut PgnGame) -> Result<(), Box<dyn Error>> {
+        let (white_name, black_name) = match self.human_color {
+            PieceColor::White => (HUMAN_NAME, self.engine.name()),
+            PieceColor::Black => (self.engine.name(), HUMAN_NAME),
+        };
+
+        pgn_game.set_tag("Event", "Local training game");
+        pgn_game.set_tag("Site", "Local terminal");
+        pgn_game.set_tag("Round", "-");
+        pgn_game.set_tag("White", white_name);
+        pgn_game.set_tag("Black", black_name);
+
+        let path = pgn_game.archive()?;
+        println!("Saved game to {}", path.display());
+
+        Ok(())
+    }
+}
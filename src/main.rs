@@ -1,20 +1,369 @@
+mod analysis_report;
+mod bitboard;
+mod board_renderer;
+mod board_square;
+mod castling_rights;
+mod chess960;
 mod chess_bot;
 mod chess_dot_com_interface;
 mod chess_move;
+mod engine_config;
+mod engine_error;
+mod engine_match;
+mod engine_registry;
+mod game_analyzer;
 mod game_board;
+mod game_termination;
+mod local_game;
+mod match_statistics;
+mod packed_move;
+mod pgn;
 mod piece;
+mod polyglot_book;
+mod position_state;
+mod search_info;
+mod search_limits;
 mod square;
-mod stockfish;
+mod uci_engine;
+mod uci_option;
+mod zobrist_keys;
 
+use crate::board_renderer::BoardRenderer;
 use crate::chess_bot::ChessBot;
+use crate::engine_config::EngineConfig;
+use crate::engine_match::EngineMatch;
+use crate::engine_registry::EngineRegistry;
+use crate::game_analyzer::GameAnalyzer;
+use crate::game_board::GameBoard;
+use crate::local_game::LocalGame;
+use crate::pgn::PgnGame;
+use crate::piece::PieceColor;
+use crate::polyglot_book::PolyglotBook;
+use crate::search_limits::SearchLimits;
+use crate::uci_engine::UciEngine;
+use anyhow::Context;
+use rand::Rng;
 use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Instant;
+
+const ENGINE_REGISTRY_PATH: &str = "./engines.txt";
+const DEFAULT_ENGINE_NAME: &str = "stockfish";
+const DEFAULT_MATCH_GAMES: u32 = 2;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
-    let mut bot = ChessBot::new().await?;
-    bot.main_loop().await?;
+    let args: Vec<String> = std::env::args().collect();
+
+    match args.get(1).map(|arg| arg.as_str()) {
+        Some("review") => {
+            let mut review_args = args[2..].to_vec();
+            let is_flipped = take_flag(&mut review_args, "--flip");
+            let path = review_args
+                .first()
+                .context("Expected a PGN file path: review <file.pgn> [--flip]")?;
+            review_games(Path::new(path), &BoardRenderer::from(is_flipped))?;
+        }
+        Some("analyze") => {
+            let mut analyze_args = args[2..].to_vec();
+            let engine_config = take_engine_config(&mut analyze_args, "--engine")?;
+            let engine_options = take_engine_options(&mut analyze_args, "--option")?;
+            if analyze_args.is_empty() {
+                return Err(Box::from(
+                    "Expected a PGN file path: analyze <file.pgn> [--engine name] [limits]",
+                ));
+            }
+            let path = analyze_args.remove(0);
+            let search_limits = match analyze_args.is_empty() {
+                true => GameAnalyzer::default_limits(),
+                false => SearchLimits::from_args(&analyze_args)?,
+            };
+            let mut analyzer =
+                GameAnalyzer::new(&engine_config, &engine_options, search_limits).await?;
+            for game in PgnGame::read_file(Path::new(&path))? {
+                println!("{}", analyzer.analyze(&game).await?);
+            }
+        }
+        Some("engines") => {
+            for engine in EngineRegistry::from_file(Path::new(ENGINE_REGISTRY_PATH))?.engines() {
+                println!("{engine}");
+            }
+        }
+        Some("play") => {
+            let mut engine_args = args[2..].to_vec();
+            let human_color = take_color(&mut engine_args)?;
+            let engine_config = take_engine_config(&mut engine_args, "--engine")?;
+            let candidate_count = take_flag_value(&mut engine_args, "--candidates")?.unwrap_or(1);
+            let ponder = take_flag(&mut engine_args, "--ponder");
+            let engine_options = take_engine_options(&mut engine_args, "--option")?;
+            let analyzer = take_game_analyzer(&mut engine_args, &engine_config).await?;
+            let book = take_book(&mut engine_args)?;
+            let starting_board = take_chess960_board(&mut engine_args)?;
+            let search_limits = SearchLimits::from_args(&engine_args)?;
+            let mut game = LocalGame::new(
+                &engine_config,
+                search_limits,
+                candidate_count,
+                &engine_options,
+                ponder,
+                human_color,
+                analyzer,
+            )
+            .await?;
+            if let Some(book) = book {
+                game.set_book(book);
+            }
+            if let Some(starting_board) = starting_board {
+                game.set_starting_board(starting_board);
+            }
+            game.play().await?;
+        }
+        Some("match") => {
+            let mut match_args = args[2..].to_vec();
+            let engine_configs = [
+                take_engine_config(&mut match_args, "--engine1")?,
+                take_engine_config(&mut match_args, "--engine2")?,
+            ];
+            let engine_options = [
+                take_engine_options(&mut match_args, "--option1")?,
+                take_engine_options(&mut match_args, "--option2")?,
+            ];
+            let game_count =
+                take_flag_value(&mut match_args, "--games")?.unwrap_or(DEFAULT_MATCH_GAMES);
+            let openings = match take_flag_value::<String>(&mut match_args, "--openings")? {
+                Some(path) => EngineMatch::load_openings(Path::new(&path))?,
+                None => vec![GameBoard::new()?],
+            };
+            let pgn_path = match take_flag_value::<String>(&mut match_args, "--pgn")? {
+                Some(path) => PathBuf::from(path),
+                None => PgnGame::get_archive_path("match")?,
+            };
+            let book = take_book(&mut match_args)?;
+            let search_limits = SearchLimits::from_args(&match_args)?;
+            let mut engine_match = EngineMatch::new(
+                [&engine_configs[0], &engine_configs[1]],
+                [&engine_options[0], &engine_options[1]],
+                search_limits,
+                openings,
+                book,
+                game_count,
+                pgn_path,
+            )
+            .await?;
+            engine_match.run().await?;
+        }
+        Some(command @ ("perft" | "divide")) => {
+            let mut perft_args = args[2..].to_vec();
+            let board = match take_flag_value::<String>(&mut perft_args, "--fen")? {
+                Some(fen) => GameBoard::from_fen(&fen)?,
+                None => GameBoard::new()?,
+            };
+            let depth = perft_args
+                .first()
+                .and_then(|depth| depth.parse::<u32>().ok())
+                .context(format!(
+                    "Expected a depth: {command} <depth> [--fen \"<fen>\"]"
+                ))?;
+            run_perft(&board, depth, command == "divide")?;
+        }
+        Some("engine-options") => {
+            let mut engine_args = args[2..].to_vec();
+            list_engine_options(&take_engine_config(&mut engine_args, "--engine")?).await?;
+        }
+        _ => {
+            let mut engine_args = args[1..].to_vec();
+            let engine_config = take_engine_config(&mut engine_args, "--engine")?;
+            let candidate_count = take_flag_value(&mut engine_args, "--candidates")?.unwrap_or(1);
+            let ponder = take_flag(&mut engine_args, "--ponder");
+            let engine_options = take_engine_options(&mut engine_args, "--option")?;
+            let analyzer = take_game_analyzer(&mut engine_args, &engine_config).await?;
+            let search_limits = SearchLimits::from_args(&engine_args)?;
+            let mut bot = ChessBot::new(
+                &engine_config,
+                search_limits,
+                candidate_count,
+                &engine_options,
+                ponder,
+                analyzer,
+            )
+            .await?;
+            bot.main_loop().await?;
+        }
+    }
 
     println!("Cheater man signing off!");
 
     Ok(())
 }
+
+fn take_flag_value<T: FromStr>(
+    args: &mut Vec<String>,
+    flag: &str,
+) -> Result<Option<T>, Box<dyn Error>> {
+    let Some(flag_index) = args.iter().position(|arg| arg == flag) else {
+        return Ok(None);
+    };
+
+    let value = args
+        .get(flag_index + 1)
+        .context(format!("Expected a value after {flag}"))?
+        .parse::<T>()
+        .ok()
+        .context(format!("Found an invalid value after {flag}"))?;
+    args.drain(flag_index..flag_index + 2);
+
+    Ok(Some(value))
+}
+
+fn take_flag(args: &mut Vec<String>, flag: &str) -> bool {
+    let flag_count = args.len();
+    args.retain(|arg| arg != flag);
+    args.len() != flag_count
+}
+
+fn take_engine_options(
+    args: &mut Vec<String>,
+    flag: &str,
+) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+    // --option "Skill Level=10" --option Threads=4
+    let mut engine_options = Vec::new();
+
+    while let Some(flag_index) = args.iter().position(|arg| arg == flag) {
+        let option = args
+            .get(flag_index + 1)
+            .context(format!("Expected Name=Value after {flag}"))?;
+        let (name, value) = option.split_once('=').unwrap_or((option.as_str(), ""));
+        engine_options.push((name.trim().to_string(), value.trim().to_string()));
+        args.drain(flag_index..flag_index + 2);
+    }
+
+    Ok(engine_options)
+}
+
+fn take_color(args: &mut Vec<String>) -> Result<PieceColor, Box<dyn Error>> {
+    // --color black lets the engine open the game
+    let color: Option<String> = take_flag_value(args, "--color")?;
+    match color.as_deref() {
+        None | Some("white") | Some("w") => Ok(PieceColor::White),
+        Some("black") | Some("b") => Ok(PieceColor::Black),
+        Some(bad_color) => Err(Box::from(format!(
+            "Expected white or black after --color, found: {bad_color}"
+        ))),
+    }
+}
+
+fn take_book(args: &mut Vec<String>) -> Result<Option<PolyglotBook>, Box<dyn Error>> {
+    // --book ./books/performance.bin
+    let path: Option<String> = take_flag_value(args, "--book")?;
+    path.map(|path| PolyglotBook::from_file(Path::new(&path)))
+        .transpose()
+}
+
+fn take_chess960_board(args: &mut Vec<String>) -> Result<Option<GameBoard>, Box<dyn Error>> {
+    // --chess960 518 plays one of the 960 start positions, --chess960 random picks one
+    let index: Option<String> = take_flag_value(args, "--chess960")?;
+    let index = match index.as_deref() {
+        None => return Ok(None),
+        Some("random") => rand::rng().random_range(0..chess960::STARTING_POSITION_COUNT),
+        Some(index) => index.parse::<usize>().ok().context(format!(
+            "Expected a position index or random after --chess960, found: {index}"
+        ))?,
+    };
+
+    let board = GameBoard::from_chess960_index(index)?;
+    println!("CHESS960 POSITION {index}: {}", board.to_fen()?);
+    Ok(Some(board))
+}
+
+fn take_engine_config(args: &mut Vec<String>, flag: &str) -> Result<EngineConfig, Box<dyn Error>> {
+    // --engine lc0 picks a registered engine, --engine ./bin/ethereal runs one by path
+    let engine_name: String =
+        take_flag_value(args, flag)?.unwrap_or_else(|| DEFAULT_ENGINE_NAME.to_string());
+    EngineRegistry::from_file(Path::new(ENGINE_REGISTRY_PATH))?.get(&engine_name)
+}
+
+async fn take_game_analyzer(
+    args: &mut Vec<String>,
+    engine_config: &EngineConfig,
+) -> Result<Option<GameAnalyzer>, Box<dyn Error>> {
+    // --analyze reviews every finished game with a separate full strength copy of the engine
+    let analysis_depth: Option<u32> = take_flag_value(args, "--analysis-depth")?;
+    if !take_flag(args, "--analyze") && analysis_depth.is_none() {
+        return Ok(None);
+    }
+
+    let search_limits = match analysis_depth {
+        Some(depth) => SearchLimits::from_depth(depth),
+        None => GameAnalyzer::default_limits(),
+    };
+    Ok(Some(
+        GameAnalyzer::new(engine_config, &[], search_limits).await?,
+    ))
+}
+
+async fn list_engine_options(engine_config: &EngineConfig) -> Result<(), Box<dyn Error>> {
+    let engine = UciEngine::new(engine_config).await?;
+    println!("ENGINE: {} by {}", engine.name(), engine.author());
+    for option in engine.options() {
+        println!("OPTION: {option}");
+    }
+
+    Ok(())
+}
+
+fn run_perft(board: &GameBoard, depth: u32, is_divide: bool) -> Result<(), Box<dyn Error>> {
+    let start_time = Instant::now();
+    let node_count = match is_divide {
+        true => {
+            let move_counts = board.divide(depth)?;
+            for (chess_move, move_node_count) in &move_counts {
+                println!("{chess_move}: {move_node_count}");
+            }
+            move_counts.iter().map(|(_, count)| count).sum()
+        }
+        false => board.perft(depth)?,
+    };
+    let elapsed = start_time.elapsed();
+
+    println!(
+        "PERFT({depth}): {node_count} nodes in {} ms ({:.0} nodes/s)",
+        elapsed.as_millis(),
+        node_count as f64 / elapsed.as_secs_f64().max(f64::EPSILON)
+    );
+
+    Ok(())
+}
+
+fn review_games(path: &Path, renderer: &BoardRenderer) -> Result<(), Box<dyn Error>> {
+    for game in PgnGame::read_file(path)? {
+        for (name, value) in &game.tags {
+            println!("{name}: {value}");
+        }
+        if let Some(comment) = &game.comment {
+            println!("{comment}");
+        }
+
+        let mut board = game.get_starting_board()?;
+        println!("{}", renderer.render(&board, None)?);
+        for pgn_move in &game.moves {
+            println!(
+                "MOVE: {} ({})",
+                pgn_move.chess_move.san_notation(&board)?,
+                pgn_move.chess_move
+            );
+            board.apply_move(&pgn_move.chess_move)?;
+            if let Some(comment) = &pgn_move.comment {
+                println!("{comment}");
+            }
+            println!("{}", renderer.render(&board, Some(&pgn_move.chess_move))?);
+        }
+
+        if let Some(termination) = board.get_termination()? {
+            println!("{termination}");
+        }
+        println!("RESULT: {}", game.result);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,91 @@
+use std::fmt::{Display, Formatter};
+
+const ELO_SCALE: f64 = 400.0;
+const CONFIDENCE_Z_SCORE: f64 = 1.96;
+
+#[derive(Clone, Copy, Default)]
+pub struct MatchStatistics {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl MatchStatistics {
+    pub fn record_result(&mut self, score: f64) {
+        // Scores are from the first engine's point of view
+        match score {
+            score if score > 0.5 => self.wins += 1,
+            score if score < 0.5 => self.losses += 1,
+            _ => self.draws += 1,
+        }
+    }
+
+    pub fn game_count(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    pub fn score(&self) -> Option<f64> {
+        match self.game_count() {
+            0 => None,
+            game_count => Some((self.wins as f64 + self.draws as f64 / 2.0) / game_count as f64),
+        }
+    }
+
+    pub fn elo_difference(&self) -> Option<f64> {
+        self.score().map(Self::score_to_elo)
+    }
+
+    pub fn elo_error_margin(&self) -> Option<f64> {
+        // 95% confidence interval from the spread of the individual game results
+        let score = self.score()?;
+        if !Self::score_to_elo(score).is_finite() {
+            return None;
+        }
+
+        let game_count = self.game_count() as f64;
+        let variance = (self.wins as f64 * (1.0 - score).powi(2)
+            + self.draws as f64 * (0.5 - score).powi(2)
+            + self.losses as f64 * score.powi(2))
+            / game_count;
+        let standard_error = (variance / game_count).sqrt();
+
+        // Too few games can stretch the interval past a perfect or zero score
+        let lower_elo = Self::score_to_elo(score - CONFIDENCE_Z_SCORE * standard_error);
+        let upper_elo = Self::score_to_elo(score + CONFIDENCE_Z_SCORE * standard_error);
+        match lower_elo.is_finite() && upper_elo.is_finite() {
+            true => Some((upper_elo - lower_elo) / 2.0),
+            false => None,
+        }
+    }
+
+    fn score_to_elo(score: f64) -> f64 {
+        // A perfect or zero score is an unbounded Elo difference
+        match score {
+            score if score <= 0.0 => f64::NEG_INFINITY,
+            score if score >= 1.0 => f64::INFINITY,
+            score => ELO_SCALE * (score / (1.0 - score)).log10(),
+        }
+    }
+}
+
+impl Display for MatchStatistics {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "W/D/L: {}/{}/{} of {} games",
+            self.wins,
+            self.draws,
+            self.losses,
+            self.game_count()
+        )?;
+
+        if let (Some(score), Some(elo_difference)) = (self.score(), self.elo_difference()) {
+            write!(f, ", score {score:.3}, Elo difference {elo_difference:+.1}")?;
+        }
+        if let Some(elo_error_margin) = self.elo_error_margin() {
+            write!(f, " +/- {elo_error_margin:.1}")?;
+        }
+
+        Ok(())
+    }
+}
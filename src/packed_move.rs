@@ -0,0 +1,138 @@
+use crate::board_square::BoardSquare;
+use crate::chess_move::ChessMove;
+use crate::game_board::GameBoard;
+use crate::piece::PieceType;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+const SQUARE_MASK: u16 = 0x3F;
+const END_SHIFT: u16 = 6;
+const PROMOTION_SHIFT: u16 = 12;
+const FLAG_SHIFT: u16 = 14;
+const PROMOTION_PIECE_TYPES: [PieceType; 4] = [
+    PieceType::Knight,
+    PieceType::Bishop,
+    PieceType::Rook,
+    PieceType::Queen,
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MoveFlag {
+    Normal,
+    Promotion,
+    EnPassant,
+    Castle,
+}
+
+// Bits from low to high: start square, end square, promotion piece, flag
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PackedMove(u16);
+
+impl PackedMove {
+    pub fn from(
+        start: BoardSquare,
+        end: BoardSquare,
+        promotion_type: Option<PieceType>,
+        flag: MoveFlag,
+    ) -> Self {
+        let promotion_bits = promotion_type
+            .and_then(|promotion_type| {
+                PROMOTION_PIECE_TYPES
+                    .iter()
+                    .position(|item| *item == promotion_type)
+            })
+            .unwrap_or(0) as u16;
+
+        Self(
+            start.index() as u16
+                | (end.index() as u16) << END_SHIFT
+                | promotion_bits << PROMOTION_SHIFT
+                | (flag as u16) << FLAG_SHIFT,
+        )
+    }
+
+    pub fn from_chess_move(
+        chess_move: &ChessMove,
+        board: &GameBoard,
+    ) -> Result<Self, Box<dyn Error>> {
+        // The flags depend on what is standing on the board, not just the two squares
+        let start = BoardSquare::from_square(&chess_move.start)?;
+        let end = BoardSquare::from_square(&chess_move.end)?;
+        let Some(moving_piece) = board.piece_at(&chess_move.start)? else {
+            return Ok(Self::from(start, end, None, MoveFlag::Normal));
+        };
+        let end_piece = board.piece_at(&chess_move.end)?;
+        let promotion_type = chess_move.get_promotion_type();
+        let is_king_side = end.file_index() > start.file_index();
+
+        // Castles are stored as king takes rook, standard chess writes them as a two file king move instead
+        let (end, flag) = match moving_piece.piece_type {
+            _ if promotion_type.is_some() => (end, MoveFlag::Promotion),
+            PieceType::King
+                if end_piece.is_some_and(|piece| {
+                    piece.color == moving_piece.color && piece.piece_type == PieceType::Rook
+                }) =>
+            {
+                (end, MoveFlag::Castle)
+            }
+            PieceType::King
+                if !board.is_chess960() && start.file_index().abs_diff(end.file_index()) == 2 =>
+            {
+                match board.get_castling_rook_square(moving_piece.color, is_king_side) {
+                    Some(rook_square) => (rook_square, MoveFlag::Castle),
+                    None => (end, MoveFlag::Normal),
+                }
+            }
+            PieceType::Pawn if start.file_index() != end.file_index() && end_piece.is_none() => {
+                (end, MoveFlag::EnPassant)
+            }
+            _ => (end, MoveFlag::Normal),
+        };
+
+        Ok(Self::from(start, end, promotion_type, flag))
+    }
+
+    pub fn start(self) -> BoardSquare {
+        BoardSquare::from_index((self.0 & SQUARE_MASK) as usize)
+    }
+
+    pub fn end(self) -> BoardSquare {
+        BoardSquare::from_index((self.0 >> END_SHIFT & SQUARE_MASK) as usize)
+    }
+
+    pub fn promotion_type(self) -> Option<PieceType> {
+        match self.flag() {
+            MoveFlag::Promotion => {
+                Some(PROMOTION_PIECE_TYPES[(self.0 >> PROMOTION_SHIFT & 3) as usize])
+            }
+            _ => None,
+        }
+    }
+
+    pub fn flag(self) -> MoveFlag {
+        match self.0 >> FLAG_SHIFT {
+            0 => MoveFlag::Normal,
+            1 => MoveFlag::Promotion,
+            2 => MoveFlag::EnPassant,
+            _ => MoveFlag::Castle,
+        }
+    }
+
+    pub fn uci_notation(self) -> String {
+        let promotion_uci_notation = self
+            .promotion_type()
+            .map_or(String::new(), |item| item.to_string());
+        format!(
+            "{}{}{}",
+            self.start().uci_notation(),
+            self.end().uci_notation(),
+            promotion_uci_notation
+        )
+    }
+}
+
+impl Display for PackedMove {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.uci_notation())
+    }
+}
@@ -0,0 +1,511 @@
+use crate::chess_move::ChessMove;
+use crate::game_board::GameBoard;
+use crate::piece::PieceColor;
+use anyhow::Context;
+use std::error::Error;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const GAME_ARCHIVE_DIRECTORY: &str = "./games";
+
+const SEVEN_TAG_ROSTER: [&str; 6] = ["Event", "Site", "Date", "Round", "White", "Black"];
+const RESULT_TOKENS: [&str; 4] = ["1-0", "0-1", "1/2-1/2", "*"];
+const UNKNOWN_RESULT: &str = "*";
+const UNKNOWN_DATE: &str = "????.??.??";
+const MAX_LINE_LENGTH: usize = 80;
+const SECONDS_PER_DAY: u64 = 86_400;
+const CHESS960_VARIANT_NAMES: [&str; 3] = ["Chess960", "Fischerandom", "Fischer Random"];
+
+enum PgnToken {
+    Tag(String, String),
+    Comment(String),
+    Move(String),
+    Result(String),
+}
+
+pub struct PgnMove {
+    pub chess_move: ChessMove,
+    pub comment: Option<String>,
+}
+
+pub struct PgnGame {
+    pub tags: Vec<(String, String)>,
+    pub comment: Option<String>,
+    pub moves: Vec<PgnMove>,
+    pub result: String,
+}
+
+impl PgnGame {
+    pub fn new() -> Self {
+        Self {
+            tags: Vec::new(),
+            comment: None,
+            moves: Vec::new(),
+            result: String::from(UNKNOWN_RESULT),
+        }
+    }
+
+    pub fn read_file(path: &Path) -> Result<Vec<Self>, Box<dyn Error>> {
+        let pgn = fs::read_to_string(path)
+            .context(format!("Failed to read PGN file: {}", path.display()))?;
+        Self::parse(&pgn)
+    }
+
+    pub fn write_file(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        fs::write(path, self.to_pgn_string()?)
+            .context(format!("Failed to write PGN file: {}", path.display()))?;
+        Ok(())
+    }
+
+    pub fn append_file(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory).context(format!(
+                "Failed to create directory: {}",
+                directory.display()
+            ))?;
+        }
+
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .context(format!("Failed to open PGN file: {}", path.display()))?;
+        file.write_all(self.to_pgn_string()?.as_bytes())
+            .context(format!("Failed to write PGN file: {}", path.display()))?;
+        Ok(())
+    }
+
+    pub fn archive(&mut self) -> Result<PathBuf, Box<dyn Error>> {
+        self.set_tag("Date", Self::get_date_tag_value(SystemTime::now()).as_str());
+        let path = Self::get_archive_path("game")?;
+        self.write_file(&path)?;
+
+        Ok(path)
+    }
+
+    pub fn get_archive_path(prefix: &str) -> Result<PathBuf, Box<dyn Error>> {
+        // Archived files are named after the time they were saved
+        fs::create_dir_all(GAME_ARCHIVE_DIRECTORY).context(format!(
+            "Failed to create game archive directory: {GAME_ARCHIVE_DIRECTORY}"
+        ))?;
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+
+        Ok(Path::new(GAME_ARCHIVE_DIRECTORY).join(format!("{prefix}_{timestamp}.pgn")))
+    }
+
+    pub fn get_tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag_name, _)| tag_name == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(tag_name, _)| tag_name == name) {
+            Some((_, tag_value)) => *tag_value = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    pub fn set_starting_board_tags(&mut self, board: &GameBoard) -> Result<(), Box<dyn Error>> {
+        // Games from the standard setup need no FEN, everything else has to say where it began
+        let starting_fen = board.to_fen()?;
+        if starting_fen != GameBoard::new()?.to_fen()? {
+            self.set_tag("SetUp", "1");
+            self.set_tag("FEN", starting_fen.as_str());
+        }
+        if board.is_chess960() {
+            self.set_tag("Variant", CHESS960_VARIANT_NAMES[0]);
+        }
+
+        Ok(())
+    }
+
+    pub fn push_move(&mut self, chess_move: ChessMove, comment: Option<String>) {
+        self.moves.push(PgnMove {
+            chess_move,
+            comment,
+        });
+    }
+
+    pub fn get_starting_board(&self) -> Result<GameBoard, Box<dyn Error>> {
+        let mut board = match self.get_tag("FEN") {
+            Some(fen) => GameBoard::from_fen(fen)?,
+            None => GameBoard::new()?,
+        };
+
+        // A Chess960 FEN without castling rights looks like any other position, so trust the tag
+        if self.get_tag("Variant").is_some_and(|variant| {
+            CHESS960_VARIANT_NAMES
+                .iter()
+                .any(|name| name.eq_ignore_ascii_case(variant))
+        }) {
+            board.set_chess960(true);
+        }
+
+        Ok(board)
+    }
+
+    pub fn parse(pgn: &str) -> Result<Vec<Self>, Box<dyn Error>> {
+        let mut games = Vec::new();
+        let mut game = Self::new();
+        let mut board: Option<GameBoard> = None;
+
+        for token in Self::tokenize(pgn)? {
+            match token {
+                PgnToken::Tag(name, value) => {
+                    // A tag after movetext means the previous game had no result token
+                    if board.is_some() {
+                        games.push(game);
+                        game = Self::new();
+                        board = None;
+                    }
+                    if name == "Result" {
+                        game.result = value.clone();
+                    }
+                    game.set_tag(&name, &value);
+                }
+                PgnToken::Comment(comment) => match game.moves.last_mut() {
+                    Some(last_move) => last_move.comment = Some(comment),
+                    None => game.comment = Some(comment),
+                },
+                PgnToken::Move(notation) => {
+                    if board.is_none() {
+                        board = Some(game.get_starting_board()?);
+                    }
+                    let current_board = board
+                        .as_mut()
+                        .context("Missing board while parsing PGN movetext")?;
+
+                    let chess_move = ChessMove::from_san_notation(&notation, current_board)
+                        .map_err(|error| {
+                            format!("Failed to parse move {notation} in PGN movetext: {error}")
+                        })?;
+                    current_board.apply_move(&chess_move)?;
+                    game.push_move(chess_move, None);
+                }
+                PgnToken::Result(result) => {
+                    game.result = result;
+                    games.push(game);
+                    game = Self::new();
+                    board = None;
+                }
+            }
+        }
+
+        if board.is_some() || !game.tags.is_empty() {
+            games.push(game);
+        }
+
+        Ok(games)
+    }
+
+    pub fn to_pgn_string(&self) -> Result<String, Box<dyn Error>> {
+        let mut pgn = String::new();
+
+        // Seven tag roster always comes first and in order, unknown values are written as "?"
+        for name in SEVEN_TAG_ROSTER {
+            let unknown_value = match name {
+                "Date" => UNKNOWN_DATE,
+                _ => "?",
+            };
+            let value = self.get_tag(name).unwrap_or(unknown_value);
+            pgn.push_str(format!("[{name} \"{}\"]\n", Self::escape_tag_value(value)).as_str());
+        }
+        pgn.push_str(format!("[Result \"{}\"]\n", self.result).as_str());
+        for (name, value) in &self.tags {
+            if SEVEN_TAG_ROSTER.contains(&name.as_str()) || name == "Result" {
+                continue;
+            }
+            pgn.push_str(format!("[{name} \"{}\"]\n", Self::escape_tag_value(value)).as_str());
+        }
+        pgn.push('\n');
+
+        let mut movetext_tokens: Vec<String> = Vec::new();
+        if let Some(comment) = &self.comment {
+            movetext_tokens.push(Self::format_comment(comment));
+        }
+
+        let mut board = self.get_starting_board()?;
+        let mut needs_move_number = true;
+        for pgn_move in &self.moves {
+            let move_number = board.fullmove_number();
+            match board.side_to_move() {
+                PieceColor::White => movetext_tokens.push(format!("{move_number}.")),
+                PieceColor::Black if needs_move_number => {
+                    movetext_tokens.push(format!("{move_number}..."))
+                }
+                PieceColor::Black => {}
+            }
+
+            movetext_tokens.push(pgn_move.chess_move.san_notation(&board)?);
+            board.apply_move(&pgn_move.chess_move)?;
+
+            // A comment interrupts the move pair so black's move needs its number repeated
+            needs_move_number = pgn_move.comment.is_some();
+            if let Some(comment) = &pgn_move.comment {
+                movetext_tokens.push(Self::format_comment(comment));
+            }
+        }
+        movetext_tokens.push(self.result.clone());
+
+        let mut line = String::new();
+        for token in movetext_tokens {
+            if !line.is_empty() && line.len() + token.len() + 1 > MAX_LINE_LENGTH {
+                pgn.push_str(line.as_str());
+                pgn.push('\n');
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(token.as_str());
+        }
+        pgn.push_str(line.as_str());
+        pgn.push_str("\n\n");
+
+        Ok(pgn)
+    }
+
+    pub fn get_date_tag_value(time: SystemTime) -> String {
+        let days_since_epoch =
+            time.duration_since(UNIX_EPOCH)
+                .map_or(0, |duration| duration.as_secs() / SECONDS_PER_DAY) as i64;
+
+        // Days to civil date conversion from Howard Hinnant's date algorithms
+        let shifted_days = days_since_epoch + 719_468;
+        let era = shifted_days.div_euclid(146_097);
+        let day_of_era = shifted_days.rem_euclid(146_097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_index = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+        let month = if month_index < 10 {
+            month_index + 3
+        } else {
+            month_index - 9
+        };
+        let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+        format!("{year:04}.{month:02}.{day:02}")
+    }
+
+    fn tokenize(pgn: &str) -> Result<Vec<PgnToken>, Box<dyn Error>> {
+        let chars: Vec<char> = pgn.chars().collect();
+        let mut tokens = Vec::new();
+        let mut variation_depth: u32 = 0;
+        let mut index = 0;
+
+        while index < chars.len() {
+            let c = chars[index];
+
+            match c {
+                '[' => {
+                    let end = Self::find_closing_char(&chars, index, ']')?;
+                    let tag: String = chars[index + 1..end].iter().collect();
+                    if variation_depth == 0 {
+                        let (name, value) = Self::parse_tag(&tag)?;
+                        tokens.push(PgnToken::Tag(name, value));
+                    }
+                    index = end + 1;
+                }
+                '{' => {
+                    let end = Self::find_closing_char(&chars, index, '}')?;
+                    let comment: String = chars[index + 1..end].iter().collect();
+                    if variation_depth == 0 {
+                        tokens.push(PgnToken::Comment(
+                            comment.split_whitespace().collect::<Vec<&str>>().join(" "),
+                        ));
+                    }
+                    index = end + 1;
+                }
+                // Rest of line comments and escaped lines are skipped entirely
+                ';' | '%' => {
+                    while index < chars.len() && chars[index] != '\n' {
+                        index += 1;
+                    }
+                }
+                '(' => {
+                    variation_depth += 1;
+                    index += 1;
+                }
+                ')' => {
+                    if variation_depth == 0 {
+                        return Err(Box::from(format!(
+                            "Found closing ')' without an opening '(' at char {index}"
+                        )));
+                    }
+                    variation_depth -= 1;
+                    index += 1;
+                }
+                _ if c.is_whitespace() => index += 1,
+                _ => {
+                    let start = index;
+                    while index < chars.len()
+                        && !chars[index].is_whitespace()
+                        && !"[]{}();".contains(chars[index])
+                    {
+                        index += 1;
+                    }
+                    let symbol: String = chars[start..index].iter().collect();
+
+                    if variation_depth > 0 || symbol.starts_with('$') {
+                        continue;
+                    }
+                    if RESULT_TOKENS.contains(&symbol.as_str()) {
+                        tokens.push(PgnToken::Result(symbol));
+                        continue;
+                    }
+
+                    // Move numbers can be glued to the move, e.g. "12.e4" or "12...e5", anything after
+                    // them is left for the SAN parser. Digits alone could be the start of "0-0"
+                    let notation = match symbol.trim_start_matches(|c: char| c.is_ascii_digit()) {
+                        rest if rest.starts_with('.') => rest.trim_start_matches('.'),
+                        _ => symbol.as_str(),
+                    };
+                    if notation.is_empty() || notation.chars().all(|item| item.is_ascii_digit()) {
+                        continue;
+                    }
+                    tokens.push(PgnToken::Move(notation.to_string()));
+                }
+            }
+        }
+
+        Ok(tokens)
+    }
+
+    fn find_closing_char(
+        chars: &[char],
+        start: usize,
+        closing_char: char,
+    ) -> Result<usize, Box<dyn Error>> {
+        let mut is_in_quotes = false;
+        let mut index = start + 1;
+
+        while index < chars.len() {
+            match chars[index] {
+                '\\' if is_in_quotes => index += 1,
+                '"' if closing_char == ']' => is_in_quotes = !is_in_quotes,
+                c if c == closing_char && !is_in_quotes => return Ok(index),
+                _ => {}
+            }
+            index += 1;
+        }
+
+        Err(Box::from(format!(
+            "Could not find closing '{closing_char}' for PGN token starting at char {start}"
+        )))
+    }
+
+    fn parse_tag(tag: &str) -> Result<(String, String), Box<dyn Error>> {
+        // Name "Value"
+        let (name, quoted_value) = tag.trim().split_once(char::is_whitespace).context(format!(
+            "Could not split PGN tag into name and value: {tag}"
+        ))?;
+
+        let value = quoted_value
+            .trim()
+            .strip_prefix('"')
+            .and_then(|value| value.strip_suffix('"'))
+            .context(format!("PGN tag value is not quoted: {tag}"))?
+            .replace("\\\"", "\"")
+            .replace("\\\\", "\\");
+
+        Ok((name.to_string(), value))
+    }
+
+    fn escape_tag_value(value: &str) -> String {
+        value.replace('\\', "\\\\").replace('"', "\\\"")
+    }
+
+    fn format_comment(comment: &str) -> String {
+        // Comments cannot be nested, so a closing brace would end the comment early
+        format!("{{{}}}", comment.replace('}', ")"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ANNOTATED_PGN: &str = r#"[Event "Test"]
+[Site "?"]
+[Date "2024.01.02"]
+[Round "1"]
+[White "White Player"]
+[Black "Black Player"]
+[Result "*"]
+
+{Opening comment} 1. e4 $1 Nf6 2. e5 {Attack the knight} 2...d5 (2... Nd5 3. d4 {Sidelines
+are skipped}) 3. exd6e.p. $2 e6 4. Nf3 Be7 5. Bc4 0-0 6.0-0 *
+"#;
+
+    fn move_notations(game: &PgnGame) -> Vec<String> {
+        game.moves
+            .iter()
+            .map(|pgn_move| pgn_move.chess_move.uci_notation())
+            .collect()
+    }
+
+    #[test]
+    fn parse_reads_annotated_movetext() {
+        let games = PgnGame::parse(ANNOTATED_PGN).unwrap();
+        assert_eq!(games.len(), 1);
+        let game = &games[0];
+
+        assert_eq!(game.get_tag("White"), Some("White Player"));
+        assert_eq!(game.result, "*");
+        assert_eq!(game.comment.as_deref(), Some("Opening comment"));
+        // Variations and NAGs are dropped, move numbers and the e.p. suffix are stripped
+        assert_eq!(
+            move_notations(game),
+            [
+                "e2e4", "g8f6", "e4e5", "d7d5", "e5d6", "e7e6", "g1f3", "f8e7", "f1c4", "e8g8",
+                "e1g1"
+            ]
+        );
+        assert_eq!(game.moves[2].comment.as_deref(), Some("Attack the knight"));
+        assert!(game.moves[3].comment.is_none());
+    }
+
+    #[test]
+    fn written_games_parse_back_the_same() {
+        let game = PgnGame::parse(ANNOTATED_PGN).unwrap().remove(0);
+        let pgn = game.to_pgn_string().unwrap();
+
+        // Black's move after a comment gets its number repeated
+        assert!(pgn.contains("2. e5 {Attack the knight} 2... d5 3. exd6 "));
+        assert!(pgn.contains("5. Bc4 O-O 6. O-O *"));
+
+        let reparsed_game = PgnGame::parse(&pgn).unwrap().remove(0);
+        assert_eq!(reparsed_game.tags, game.tags);
+        assert_eq!(reparsed_game.comment, game.comment);
+        assert_eq!(move_notations(&reparsed_game), move_notations(&game));
+        let comments: Vec<Option<String>> =
+            game.moves.iter().map(|item| item.comment.clone()).collect();
+        let reparsed_comments: Vec<Option<String>> = reparsed_game
+            .moves
+            .iter()
+            .map(|item| item.comment.clone())
+            .collect();
+        assert_eq!(reparsed_comments, comments);
+        assert_eq!(reparsed_game.to_pgn_string().unwrap(), pgn);
+    }
+
+    #[test]
+    fn unknown_roster_tags_are_written_as_placeholders() {
+        let pgn = PgnGame::new().to_pgn_string().unwrap();
+        assert!(pgn.contains("[Date \"????.??.??\"]\n"));
+        assert!(pgn.contains("[Round \"?\"]\n"));
+        assert!(pgn.contains("[Result \"*\"]\n"));
+    }
+
+    #[test]
+    fn unmatched_closing_parenthesis_is_an_error() {
+        assert!(PgnGame::parse("1. e4 e5 ) 2. Nf3 *").is_err());
+        assert!(PgnGame::parse("1. e4 (1. d4 (1. c4)) e5 *").is_ok());
+    }
+}
@@ -1,13 +1,13 @@
 use std::error::Error;
 use std::fmt::{Display, Formatter};
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Hash)]
 pub enum PieceColor {
     Black,
     White,
 }
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Hash)]
 pub enum PieceType {
     Pawn,
     Bishop,
@@ -17,12 +17,47 @@ pub enum PieceType {
     King,
 }
 
-#[derive(Clone, PartialEq)]
+#[derive(Clone, PartialEq, Hash)]
 pub struct Piece {
     pub color: PieceColor,
     pub piece_type: PieceType,
 }
 
+impl PieceColor {
+    pub fn opposite(&self) -> PieceColor {
+        match self {
+            PieceColor::White => PieceColor::Black,
+            PieceColor::Black => PieceColor::White,
+        }
+    }
+}
+
+impl PieceType {
+    pub fn san_char(&self) -> char {
+        match self {
+            PieceType::Pawn => 'P',
+            PieceType::Knight => 'N',
+            PieceType::Bishop => 'B',
+            PieceType::Rook => 'R',
+            PieceType::Queen => 'Q',
+            PieceType::King => 'K',
+        }
+    }
+
+    pub fn from_san_char(c: char) -> Result<PieceType, Box<dyn Error>> {
+        match c {
+            'N' => Ok(PieceType::Knight),
+            'B' => Ok(PieceType::Bishop),
+            'R' => Ok(PieceType::Rook),
+            'Q' => Ok(PieceType::Queen),
+            'K' => Ok(PieceType::King),
+            _ => Err(Box::from(format!(
+                "Could not parse SAN char '{c}' into a valid piece type"
+            ))),
+        }
+    }
+}
+
 impl Piece {
     pub fn from(color: PieceColor, piece_type: PieceType) -> Piece {
         Piece { color, piece_type }
@@ -51,6 +86,67 @@ impl Piece {
             ))),
         }
     }
+
+    pub fn from_fen_char(c: char) -> Result<Piece, Box<dyn Error>> {
+        // Uppercase is white, lowercase is black, letters otherwise match the UCI piece letters
+        let color = match c.is_ascii_uppercase() {
+            true => PieceColor::White,
+            false => PieceColor::Black,
+        };
+        let piece_type = match c.to_ascii_lowercase() {
+            'p' => PieceType::Pawn,
+            'n' => PieceType::Knight,
+            'b' => PieceType::Bishop,
+            'r' => PieceType::Rook,
+            'q' => PieceType::Queen,
+            'k' => PieceType::King,
+            _ => {
+                return Err(Box::from(format!(
+                    "Could not parse FEN char '{c}' into a valid piece"
+                )));
+            }
+        };
+
+        Ok(Piece::from(color, piece_type))
+    }
+
+    pub fn fen_char(&self) -> char {
+        let c = self.piece_type.san_char();
+
+        match self.color {
+            PieceColor::White => c,
+            PieceColor::Black => c.to_ascii_lowercase(),
+        }
+    }
+
+    pub fn unicode_char(&self) -> char {
+        match (self.color, self.piece_type) {
+            (PieceColor::White, PieceType::King) => '♔',
+            (PieceColor::White, PieceType::Queen) => '♕',
+            (PieceColor::White, PieceType::Rook) => '♖',
+            (PieceColor::White, PieceType::Bishop) => '♗',
+            (PieceColor::White, PieceType::Knight) => '♘',
+            (PieceColor::White, PieceType::Pawn) => '♙',
+            (PieceColor::Black, PieceType::King) => '♚',
+            (PieceColor::Black, PieceType::Queen) => '♛',
+            (PieceColor::Black, PieceType::Rook) => '♜',
+            (PieceColor::Black, PieceType::Bishop) => '♝',
+            (PieceColor::Black, PieceType::Knight) => '♞',
+            (PieceColor::Black, PieceType::Pawn) => '♟',
+        }
+    }
+
+    pub fn uci_char_to_promotion_type(c: char) -> Result<PieceType, Box<dyn Error>> {
+        match c {
+            'q' => Ok(PieceType::Queen),
+            'r' => Ok(PieceType::Rook),
+            'b' => Ok(PieceType::Bishop),
+            'n' => Ok(PieceType::Knight),
+            _ => Err(Box::from(format!(
+                "Could not parse char '{c}' into a valid promotion type"
+            ))),
+        }
+    }
 }
 
 impl Display for PieceType {
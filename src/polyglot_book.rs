@@ -0,0 +1,123 @@
+use crate::zobrist_keys::ZobristKeys;
+use anyhow::Context;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+const ENTRY_SIZE: usize = 16;
+const PROMOTION_CHARS: [&str; 5] = ["", "n", "b", "r", "q"];
+
+static POLYGLOT_KEYS: ZobristKeys = ZobristKeys::polyglot();
+
+pub struct PolyglotBook {
+    entries: Vec<BookEntry>,
+}
+
+pub struct BookEntry {
+    pub key: u64,
+    pub raw_move: u16,
+    pub weight: u16,
+}
+
+impl PolyglotBook {
+    pub fn from_file(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let bytes =
+            fs::read(path).context(format!("Failed to read opening book: {}", path.display()))?;
+        if bytes.len() % ENTRY_SIZE != 0 {
+            return Err(Box::from(format!(
+                "Opening book {} is not a Polyglot .bin file",
+                path.display()
+            )));
+        }
+
+        // 16 byte big endian entries of key, move, weight and a learn value we have no use for
+        let mut entries: Vec<BookEntry> = bytes
+            .chunks_exact(ENTRY_SIZE)
+            .map(|entry| BookEntry {
+                key: u64::from_be_bytes(entry[0..8].try_into().unwrap_or_default()),
+                raw_move: u16::from_be_bytes([entry[8], entry[9]]),
+                weight: u16::from_be_bytes([entry[10], entry[11]]),
+            })
+            .collect();
+        // Books are sorted already but lookups rely on it so don't take the file's word for it
+        entries.sort_by_key(|entry| entry.key);
+
+        Ok(Self { entries })
+    }
+
+    pub fn keys(&self) -> &ZobristKeys {
+        &POLYGLOT_KEYS
+    }
+
+    pub fn get_entries(&self, key: u64) -> &[BookEntry] {
+        let start = self.entries.partition_point(|entry| entry.key < key);
+        let end = self.entries.partition_point(|entry| entry.key <= key);
+        &self.entries[start..end]
+    }
+}
+
+impl BookEntry {
+    pub fn uci_notation(&self) -> String {
+        // Bits from low to high: to file, to rank, from file, from rank, promotion piece
+        let square_notation = |file: u16, rank: u16| {
+            format!(
+                "{}{}",
+                (b'a' + file as u8) as char,
+                (b'1' + rank as u8) as char
+            )
+        };
+        let to_square = square_notation(self.raw_move & 7, (self.raw_move >> 3) & 7);
+        let from_square = square_notation((self.raw_move >> 6) & 7, (self.raw_move >> 9) & 7);
+        let promotion = PROMOTION_CHARS
+            .get(((self.raw_move >> 12) & 7) as usize)
+            .unwrap_or(&"");
+
+        format!("{from_square}{to_square}{promotion}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chess_move::ChessMove;
+    use crate::game_board::GameBoard;
+
+    fn book_entry(raw_move: u16) -> BookEntry {
+        BookEntry {
+            key: 0,
+            raw_move,
+            weight: 1,
+        }
+    }
+
+    #[test]
+    fn book_moves_decode_to_uci() {
+        // e2e4: from e2 (file 4, rank 1) to e4 (file 4, rank 3)
+        assert_eq!(
+            book_entry(4 << 6 | 1 << 9 | 4 | 3 << 3).uci_notation(),
+            "e2e4"
+        );
+        // Castles are stored as the king taking its own rook and left that way for move matching
+        assert_eq!(book_entry(4 << 6 | 7).uci_notation(), "e1h1");
+        assert_eq!(book_entry(4 << 6 | 7 << 9 | 7 << 3).uci_notation(), "e8a8");
+
+        // Which the board turns into the standard two file king move
+        let board = GameBoard::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        let book_move =
+            ChessMove::from_uci_notation(&book_entry(4 << 6 | 7).uci_notation()).unwrap();
+        let castle_move = board.find_legal_move(&book_move).unwrap().unwrap();
+        assert_eq!(castle_move.uci_notation(), "e1g1");
+    }
+
+    #[test]
+    fn book_promotions_decode_every_piece_type() {
+        // a7a8 with promotion bits 1 to 4 for knight, bishop, rook and queen
+        let a7a8 = 6 << 9 | 7 << 3;
+        let promotions: Vec<String> = (1..=4)
+            .map(|promotion| book_entry(a7a8 | promotion << 12).uci_notation())
+            .collect();
+
+        assert_eq!(promotions, ["a7a8n", "a7a8b", "a7a8r", "a7a8q"]);
+        assert_eq!(book_entry(a7a8).uci_notation(), "a7a8");
+    }
+}
@@ -0,0 +1,116 @@
+use crate::castling_rights::CastlingRights;
+use crate::piece::PieceColor;
+use crate::square::Square;
+use anyhow::Context;
+use std::error::Error;
+
+#[derive(Clone, PartialEq)]
+pub struct PositionState {
+    pub side_to_move: PieceColor,
+    pub castling_rights: CastlingRights,
+    pub en_passant_square: Option<(usize, usize)>,
+    pub halfmove_clock: u32,
+    pub fullmove_number: u32,
+}
+
+impl PositionState {
+    pub fn new() -> Self {
+        Self {
+            side_to_move: PieceColor::White,
+            castling_rights: CastlingRights::all(),
+            en_passant_square: None,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+        }
+    }
+
+    pub fn from_fen_fields<'a>(
+        mut fields: impl Iterator<Item = &'a str>,
+        fen: &str,
+        king_files: [Option<usize>; 2],
+        rook_files: [Vec<usize>; 2],
+    ) -> Result<Self, Box<dyn Error>> {
+        // b KQkq e3 0 1
+        let side_to_move = match fields
+            .next()
+            .context(format!("Unable to find side to move in FEN: {fen}"))?
+        {
+            "w" => PieceColor::White,
+            "b" => PieceColor::Black,
+            bad_side => {
+                return Err(Box::from(format!(
+                    "Found invalid side to move '{bad_side}' in FEN: {fen}"
+                )));
+            }
+        };
+
+        let castling_rights = CastlingRights::from_fen_notation(
+            fields
+                .next()
+                .context(format!("Unable to find castling rights in FEN: {fen}"))?,
+            king_files,
+            rook_files,
+        )?;
+
+        let en_passant_square = match fields
+            .next()
+            .context(format!("Unable to find en passant square in FEN: {fen}"))?
+        {
+            "-" => None,
+            notation => {
+                let square = Square::from_uci_notation(notation)?;
+                Some((square.rank_index()?, square.file_index()?))
+            }
+        };
+
+        // Move clocks are commonly left off of shortened FEN strings
+        let halfmove_clock = fields
+            .next()
+            .map_or(Ok(0), |clock| clock.parse::<u32>())
+            .context(format!("Unable to parse halfmove clock in FEN: {fen}"))?;
+        let fullmove_number = fields
+            .next()
+            .map_or(Ok(1), |number| number.parse::<u32>())
+            .context(format!("Unable to parse fullmove number in FEN: {fen}"))?;
+
+        Ok(Self {
+            side_to_move,
+            castling_rights,
+            en_passant_square,
+            halfmove_clock,
+            fullmove_number,
+        })
+    }
+
+    pub fn fen_fields(&self, is_chess960: bool) -> Result<String, Box<dyn Error>> {
+        let en_passant_notation = match self.en_passant_square {
+            Some((rank, file)) => format!(
+                "{}{}",
+                Square::index_to_file(file)?,
+                Square::index_to_rank(rank)?
+            ),
+            None => String::from("-"),
+        };
+
+        Ok(format!(
+            "{} {} {} {} {}",
+            self.side_to_move,
+            self.castling_rights.fen_notation(is_chess960),
+            en_passant_notation,
+            self.halfmove_clock,
+            self.fullmove_number
+        ))
+    }
+
+    pub fn advance_turn(&mut self, resets_halfmove_clock: bool) {
+        // Pawn moves and captures reset the fifty move counter, black moving completes a full move
+        self.halfmove_clock = match resets_halfmove_clock {
+            true => 0,
+            false => self.halfmove_clock + 1,
+        };
+        if self.side_to_move == PieceColor::Black {
+            self.fullmove_number += 1;
+        }
+        self.side_to_move = self.side_to_move.opposite();
+    }
+}
@@ -0,0 +1,347 @@
+use crate::chess_move::ChessMove;
+use crate::piece::PieceColor;
+use anyhow::Context;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+
+const CENTIPAWNS_PER_PAWN: f64 = 100.0;
+const MATE_CENTIPAWNS: i32 = 100_000;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Score {
+    Centipawns(i32),
+    Mate(i32),
+}
+
+#[derive(Clone, Copy, PartialEq, Default)]
+pub enum ScoreBound {
+    #[default]
+    Exact,
+    Lower,
+    Upper,
+}
+
+#[derive(Clone, Default)]
+pub struct SearchInfo {
+    pub depth: Option<u32>,
+    pub selective_depth: Option<u32>,
+    pub multi_pv: Option<u32>,
+    pub score: Option<Score>,
+    pub score_bound: ScoreBound,
+    pub nodes: Option<u64>,
+    pub nodes_per_second: Option<u64>,
+    pub hash_full: Option<u32>,
+    pub table_base_hits: Option<u64>,
+    pub time: Option<Duration>,
+    pub principal_variation: Vec<ChessMove>,
+}
+
+pub struct SearchResult {
+    pub best_move: ChessMove,
+    pub ponder_move: Option<ChessMove>,
+    pub info: SearchInfo,
+    pub candidates: Vec<SearchInfo>,
+}
+
+impl SearchResult {
+    pub fn from_search_output(output_lines: &[String]) -> Result<Self, Box<dyn Error>> {
+        // Later info lines are deeper, so keep the last scored line for each multipv rank
+        let mut candidates: Vec<SearchInfo> = Vec::new();
+        for line in output_lines.iter().filter(|line| line.starts_with("info")) {
+            // One garbled line should not cost the whole search its result
+            let info = match SearchInfo::from_info_line(line) {
+                Ok(info) => info,
+                Err(error) => {
+                    println!("{error}, skipping the info line");
+                    continue;
+                }
+            };
+            if info.score.is_none() || info.principal_variation.is_empty() {
+                continue;
+            }
+
+            let rank = info.multi_pv.unwrap_or(1).max(1) as usize;
+            if rank > candidates.len() {
+                candidates.resize(rank, SearchInfo::default());
+            }
+            candidates[rank - 1] = info;
+        }
+        candidates.retain(|info| !info.principal_variation.is_empty());
+
+        // bestmove e2e4 ponder e7e5
+        let mut best_move_tokens = output_lines
+            .iter()
+            .rev()
+            .find_map(|line| line.strip_prefix("bestmove "))
+            .context("Could not find best move in engine output")?
+            .split_whitespace();
+        let best_move_notation = best_move_tokens
+            .next()
+            .context("Could not parse best move from engine output")?;
+        if best_move_notation == "(none)" {
+            return Err(Box::from("Engine found no legal moves in the position"));
+        }
+        let ponder_move = match (best_move_tokens.next(), best_move_tokens.next()) {
+            (Some("ponder"), Some(notation)) => Some(ChessMove::from_uci_notation(notation)?),
+            _ => None,
+        };
+
+        Ok(Self {
+            best_move: ChessMove::from_uci_notation(best_move_notation)?,
+            ponder_move,
+            info: candidates.first().cloned().unwrap_or_default(),
+            candidates,
+        })
+    }
+}
+
+impl Score {
+    pub fn for_white(&self, side_to_move: PieceColor) -> Score {
+        // Engines always report the score from the point of view of the side to move
+        match (side_to_move, *self) {
+            (PieceColor::White, score) => score,
+            (PieceColor::Black, Score::Centipawns(centipawns)) => Score::Centipawns(-centipawns),
+            (PieceColor::Black, Score::Mate(moves)) => Score::Mate(-moves),
+        }
+    }
+
+    pub fn to_centipawns(self) -> i32 {
+        // Any forced mate outweighs every material score, being mated (mate 0) included
+        match self {
+            Score::Centipawns(centipawns) => centipawns,
+            Score::Mate(moves) if moves > 0 => MATE_CENTIPAWNS,
+            Score::Mate(_) => -MATE_CENTIPAWNS,
+        }
+    }
+}
+
+impl SearchInfo {
+    pub fn from_info_line(line: &str) -> Result<Self, Box<dyn Error>> {
+        // info depth 12 seldepth 18 multipv 1 score cp 35 lowerbound nodes 1234 nps 5678 hashfull 12 tbhits 0 time 80 pv e2e4 e7e5
+        let mut info = Self::default();
+        let mut tokens = line.split_whitespace().skip_while(|token| *token == "info");
+
+        while let Some(token) = tokens.next() {
+            match token {
+                "depth" => info.depth = Some(Self::parse_value(tokens.next(), token, line)?),
+                "seldepth" => {
+                    info.selective_depth = Some(Self::parse_value(tokens.next(), token, line)?)
+                }
+                "multipv" => info.multi_pv = Some(Self::parse_value(tokens.next(), token, line)?),
+                "nodes" => info.nodes = Some(Self::parse_value(tokens.next(), token, line)?),
+                "nps" => {
+                    info.nodes_per_second = Some(Self::parse_value(tokens.next(), token, line)?)
+                }
+                "hashfull" => info.hash_full = Some(Self::parse_value(tokens.next(), token, line)?),
+                "tbhits" => {
+                    info.table_base_hits = Some(Self::parse_value(tokens.next(), token, line)?)
+                }
+                "time" => {
+                    info.time = Some(Duration::from_millis(Self::parse_value(
+                        tokens.next(),
+                        token,
+                        line,
+                    )?))
+                }
+                "score" => {
+                    let score_type = tokens
+                        .next()
+                        .context(format!("Missing score type in info line: {line}"))?;
+                    let value = Self::parse_value(tokens.next(), score_type, line)?;
+                    info.score = match score_type {
+                        "cp" => Some(Score::Centipawns(value)),
+                        "mate" => Some(Score::Mate(value)),
+                        _ => {
+                            return Err(Box::from(format!(
+                                "Found unknown score type '{score_type}' in info line: {line}"
+                            )));
+                        }
+                    };
+                }
+                "lowerbound" => info.score_bound = ScoreBound::Lower,
+                "upperbound" => info.score_bound = ScoreBound::Upper,
+                // Principal variation and free text strings both run to the end of the line, a
+                // pv is cut short at the first move that can't be read (e.g. a null move "0000")
+                "pv" => {
+                    for notation in tokens.by_ref() {
+                        match ChessMove::from_uci_notation(notation) {
+                            Ok(chess_move) if Self::is_on_board(&chess_move) => {
+                                info.principal_variation.push(chess_move)
+                            }
+                            _ => break,
+                        }
+                    }
+                }
+                "string" => break,
+                _ => {}
+            }
+        }
+
+        Ok(info)
+    }
+
+    fn is_on_board(chess_move: &ChessMove) -> bool {
+        // Squares only check their rank and file once they're used
+        [&chess_move.start, &chess_move.end]
+            .iter()
+            .all(|square| square.rank_index().is_ok() && square.file_index().is_ok())
+    }
+
+    fn parse_value<T: std::str::FromStr>(
+        value: Option<&str>,
+        name: &str,
+        line: &str,
+    ) -> Result<T, Box<dyn Error>> {
+        value
+            .and_then(|value| value.parse::<T>().ok())
+            .ok_or_else(|| Box::from(format!("Could not parse {name} value in info line: {line}")))
+    }
+}
+
+impl Display for Score {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Score::Centipawns(centipawns) => {
+                write!(f, "{:+.2}", *centipawns as f64 / CENTIPAWNS_PER_PAWN)
+            }
+            Score::Mate(moves) => write!(f, "#{moves}"),
+        }
+    }
+}
+
+impl Display for SearchInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut output = match self.score {
+            Some(score) => score.to_string(),
+            None => String::from("?"),
+        };
+        match self.score_bound {
+            ScoreBound::Exact => {}
+            ScoreBound::Lower => output.push_str(" (lower bound)"),
+            ScoreBound::Upper => output.push_str(" (upper bound)"),
+        }
+
+        let optional_fields = [
+            ("multipv", self.multi_pv.map(u64::from)),
+            ("depth", self.depth.map(u64::from)),
+            ("seldepth", self.selective_depth.map(u64::from)),
+            ("nodes", self.nodes),
+            ("nps", self.nodes_per_second),
+            ("hashfull", self.hash_full.map(u64::from)),
+            ("tbhits", self.table_base_hits),
+            ("time", self.time.map(|time| time.as_millis() as u64)),
+        ];
+        for (name, value) in optional_fields {
+            if let Some(value) = value {
+                output.push_str(format!(" {name} {value}").as_str());
+            }
+        }
+
+        if !self.principal_variation.is_empty() {
+            let principal_variation: Vec<String> = self
+                .principal_variation
+                .iter()
+                .map(|chess_move| chess_move.uci_notation())
+                .collect();
+            output.push_str(format!(" pv {}", principal_variation.join(" ")).as_str());
+        }
+
+        write!(f, "{}", output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn principal_variation(info: &SearchInfo) -> Vec<String> {
+        info.principal_variation
+            .iter()
+            .map(|chess_move| chess_move.uci_notation())
+            .collect()
+    }
+
+    #[test]
+    fn info_lines_read_centipawn_and_mate_scores() {
+        let info = SearchInfo::from_info_line(
+            "info depth 12 seldepth 18 multipv 1 score cp 35 nodes 1234 nps 5678 hashfull 12 tbhits 0 time 80 pv e2e4 e7e5",
+        )
+        .unwrap();
+        assert_eq!(info.depth, Some(12));
+        assert_eq!(info.selective_depth, Some(18));
+        assert_eq!(info.multi_pv, Some(1));
+        assert!(info.score == Some(Score::Centipawns(35)));
+        assert!(info.score_bound == ScoreBound::Exact);
+        assert_eq!(info.nodes, Some(1234));
+        assert_eq!(info.nodes_per_second, Some(5678));
+        assert_eq!(info.hash_full, Some(12));
+        assert_eq!(info.table_base_hits, Some(0));
+        assert_eq!(info.time, Some(Duration::from_millis(80)));
+        assert_eq!(principal_variation(&info), ["e2e4", "e7e5"]);
+
+        let info =
+            SearchInfo::from_info_line("info depth 20 score mate -3 upperbound pv h7h8q").unwrap();
+        assert!(info.score == Some(Score::Mate(-3)));
+        assert!(info.score_bound == ScoreBound::Upper);
+        assert_eq!(principal_variation(&info), ["h7h8q"]);
+
+        let info = SearchInfo::from_info_line("info depth 9 score cp -120 lowerbound").unwrap();
+        assert!(info.score == Some(Score::Centipawns(-120)));
+        assert!(info.score_bound == ScoreBound::Lower);
+        assert!(info.principal_variation.is_empty());
+    }
+
+    #[test]
+    fn info_lines_skip_unknown_tokens_and_cut_bad_principal_variations() {
+        let info = SearchInfo::from_info_line(
+            "info depth 5 currmove e2e4 currmovenumber 1 wdl 500 300 200 score cp 10 pv g1f3 0000 d7d5",
+        )
+        .unwrap();
+        assert!(info.score == Some(Score::Centipawns(10)));
+        assert_eq!(principal_variation(&info), ["g1f3"]);
+
+        assert!(SearchInfo::from_info_line("info depth 5 score wdl 10").is_err());
+        assert!(SearchInfo::from_info_line("info depth five").is_err());
+    }
+
+    #[test]
+    fn search_output_keeps_the_deepest_line_for_each_multipv_rank() {
+        let output_lines: Vec<String> = [
+            "info string NNUE evaluation enabled",
+            "info depth 1 multipv 1 score cp 20 pv d2d4",
+            "info depth 1 multipv 2 score cp 10 pv e2e4",
+            "info depth 2 multipv 1 score cp 30 pv e2e4 e7e5",
+            "info depth 2 multipv 2 score garbage 5 pv d2d4",
+            "info depth 2 multipv 2 score cp 25 pv d2d4 d7d5",
+            "info depth 3 currmove e2e4 currmovenumber 1",
+            "bestmove e2e4 ponder e7e5",
+        ]
+        .iter()
+        .map(|line| line.to_string())
+        .collect();
+
+        let search_result = SearchResult::from_search_output(&output_lines).unwrap();
+        assert_eq!(search_result.best_move.uci_notation(), "e2e4");
+        assert_eq!(
+            search_result
+                .ponder_move
+                .map(|chess_move| chess_move.uci_notation()),
+            Some(String::from("e7e5"))
+        );
+        assert_eq!(search_result.candidates.len(), 2);
+        assert_eq!(principal_variation(&search_result.info), ["e2e4", "e7e5"]);
+        assert!(search_result.candidates[1].score == Some(Score::Centipawns(25)));
+        assert_eq!(
+            principal_variation(&search_result.candidates[1]),
+            ["d2d4", "d7d5"]
+        );
+    }
+
+    #[test]
+    fn search_output_without_a_move_is_an_error() {
+        let output_lines = vec![String::from("bestmove (none)")];
+        assert!(SearchResult::from_search_output(&output_lines).is_err());
+        assert!(SearchResult::from_search_output(&[]).is_err());
+    }
+}
@@ -0,0 +1,117 @@
+use anyhow::Context;
+use std::error::Error;
+use std::time::Duration;
+
+const DEFAULT_DEPTH: u32 = 1;
+
+#[derive(Clone, Default)]
+pub struct SearchLimits {
+    pub depth: Option<u32>,
+    pub nodes: Option<u64>,
+    pub move_time: Option<Duration>,
+    pub white_time: Option<Duration>,
+    pub black_time: Option<Duration>,
+    pub white_increment: Option<Duration>,
+    pub black_increment: Option<Duration>,
+    pub moves_to_go: Option<u32>,
+    pub mate: Option<u32>,
+    pub infinite: bool,
+    pub ponder: bool,
+}
+
+impl SearchLimits {
+    pub fn from_depth(depth: u32) -> Self {
+        Self {
+            depth: Some(depth),
+            ..Self::default()
+        }
+    }
+
+    pub fn from_args(args: &[String]) -> Result<Self, Box<dyn Error>> {
+        // --depth 12 --movetime 1000 --wtime 60000 --btime 60000 --winc 0 --binc 0 --infinite
+        let mut limits = Self::default();
+        let mut args_iter = args.iter();
+
+        while let Some(arg) = args_iter.next() {
+            if arg == "--infinite" {
+                limits.infinite = true;
+                continue;
+            }
+
+            let value = args_iter
+                .next()
+                .context(format!("Expected a value after search limit {arg}"))?;
+            let number = value.parse::<u64>().context(format!(
+                "Search limit {arg} expects a number, found: {value}"
+            ))?;
+
+            match arg.as_str() {
+                "--depth" => limits.depth = Some(number as u32),
+                "--nodes" => limits.nodes = Some(number),
+                "--movetime" => limits.move_time = Some(Duration::from_millis(number)),
+                "--wtime" => limits.white_time = Some(Duration::from_millis(number)),
+                "--btime" => limits.black_time = Some(Duration::from_millis(number)),
+                "--winc" => limits.white_increment = Some(Duration::from_millis(number)),
+                "--binc" => limits.black_increment = Some(Duration::from_millis(number)),
+                "--movestogo" => limits.moves_to_go = Some(number as u32),
+                "--mate" => limits.mate = Some(number as u32),
+                _ => return Err(Box::from(format!("Unknown search limit: {arg}"))),
+            }
+        }
+
+        // Keep the old quick search when nothing was asked for
+        match limits.go_command() == "go" {
+            true => Ok(Self::from_depth(DEFAULT_DEPTH)),
+            false => Ok(limits),
+        }
+    }
+
+    pub fn ensure_finite(&self) -> Result<(), Box<dyn Error>> {
+        // Nothing would ever stop an infinite search so we would wait on bestmove forever
+        match self.infinite {
+            true => Err(Box::from(
+                "Infinite search never reports a best move without being stopped",
+            )),
+            false => Ok(()),
+        }
+    }
+
+    pub fn go_command(&self) -> String {
+        let mut command = String::from("go");
+
+        let optional_limits = [
+            ("depth", self.depth.map(u64::from)),
+            ("nodes", self.nodes),
+            ("movetime", self.move_time.map(Self::to_millis)),
+            ("wtime", self.white_time.map(Self::to_millis)),
+            ("btime", self.black_time.map(Self::to_millis)),
+            ("winc", self.white_increment.map(Self::to_millis)),
+            ("binc", self.black_increment.map(Self::to_millis)),
+            ("movestogo", self.moves_to_go.map(u64::from)),
+            ("mate", self.mate.map(u64::from)),
+        ];
+        for (name, value) in optional_limits {
+            if let Some(value) = value {
+                command.push_str(format!(" {name} {value}").as_str());
+            }
+        }
+
+        if self.infinite {
+            command.push_str(" infinite");
+        }
+        if self.ponder {
+            command.push_str(" ponder");
+        }
+
+        command
+    }
+
+    pub fn time_budget(&self) -> Option<Duration> {
+        // Depth, node and mate searches give no hint of how long they will take
+        self.move_time.or(self.white_time.max(self.black_time))
+    }
+
+    fn to_millis(duration: Duration) -> u64 {
+        duration.as_millis() as u64
+    }
+}
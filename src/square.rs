@@ -18,7 +18,15 @@ impl Square {
         Self { rank, file, piece }
     }
 
-    pub fn from_uci_notation(notation: &String) -> Result<Self, Box<dyn Error>> {
+    pub fn from_indexes(rank_index: usize, file_index: usize, piece: Option<Piece>) -> Self {
+        Self {
+            rank: RANKS[rank_index].to_string(),
+            file: FILES[file_index].to_string(),
+            piece,
+        }
+    }
+
+    pub fn from_uci_notation(notation: &str) -> Result<Self, Box<dyn Error>> {
         let mut chars = notation.chars();
 
         let file = chars
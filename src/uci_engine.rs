@@ -0,0 +1,587 @@
+use crate::chess_move::ChessMove;
+use crate::engine_config::EngineConfig;
+use crate::engine_error::EngineError;
+use crate::game_board::GameBoard;
+use crate::search_info::{SearchInfo, SearchResult};
+use crate::search_limits::SearchLimits;
+use crate::uci_option::{UciOption, UciOptionType};
+use anyhow::Context;
+use std::error::Error;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, Command};
+use tokio::sync::mpsc::{UnboundedReceiver, unbounded_channel};
+use tokio::time::{Instant, timeout, timeout_at};
+
+const MOVE_LIST_CAPACITY: usize = 1000;
+const READY_TIMEOUT: Duration = Duration::from_secs(10);
+const DEFAULT_SEARCH_TIMEOUT: Duration = Duration::from_secs(300);
+const SEARCH_TIMEOUT_GRACE: Duration = Duration::from_secs(5);
+const STOP_GRACE: Duration = Duration::from_secs(2);
+const EXIT_STATUS_WAIT: Duration = Duration::from_millis(500);
+const MAX_RESTART_ATTEMPTS: u32 = 3;
+const CHESS960_OPTION_NAME: &str = "UCI_Chess960";
+
+pub struct UciEngine {
+    config: EngineConfig,
+    process: Child,
+    input: ChildStdin,
+    output: UnboundedReceiver<String>,
+    pending_output: Vec<String>,
+    set_position_command: String,
+    name: String,
+    author: String,
+    options: Vec<UciOption>,
+    applied_options: Vec<(String, String)>,
+    multi_pv: u32,
+    current_search: Option<SearchLimits>,
+    ponder_move: Option<ChessMove>,
+    is_ponder_enabled: bool,
+    search_deadline: Option<Instant>,
+    is_stopping: bool,
+    restart_count: u32,
+}
+
+impl UciEngine {
+    pub async fn new(config: &EngineConfig) -> Result<UciEngine, Box<dyn Error>> {
+        let (process, input, output) = Self::spawn_process(config)?;
+
+        let mut engine = UciEngine {
+            config: config.clone(),
+            process,
+            input,
+            output,
+            pending_output: Vec::new(),
+            set_position_command: String::with_capacity(MOVE_LIST_CAPACITY),
+            name: String::new(),
+            author: String::new(),
+            options: Vec::new(),
+            applied_options: Vec::new(),
+            multi_pv: 1,
+            current_search: None,
+            ponder_move: None,
+            is_ponder_enabled: false,
+            search_deadline: None,
+            is_stopping: false,
+            restart_count: 0,
+        };
+        engine.initialize_uci().await?;
+        engine.reset(&GameBoard::new()?).await?;
+
+        Ok(engine)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn author(&self) -> &str {
+        &self.author
+    }
+
+    pub fn options(&self) -> &[UciOption] {
+        &self.options
+    }
+
+    pub fn has_option(&self, name: &str) -> bool {
+        self.options
+            .iter()
+            .any(|option| option.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn is_searching(&self) -> bool {
+        self.current_search.is_some()
+    }
+
+    pub async fn set_option(&mut self, name: &str, value: &str) -> Result<(), Box<dyn Error>> {
+        // Option names are case insensitive, but the engine should see the name it advertised
+        let option = self
+            .options
+            .iter()
+            .find(|option| option.name.eq_ignore_ascii_case(name))
+            .context(format!("{} has no option named {name}", self.config.name))?;
+        option.validate_value(value)?;
+
+        // Remember the option so a restarted engine is configured the same way
+        let name = option.name.clone();
+        self.applied_options
+            .retain(|(applied_name, _)| *applied_name != name);
+        self.applied_options.push((name.clone(), value.to_string()));
+
+        if let Err(error) = self.send_option(&name, value).await {
+            self.recover_from(error).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn enable_ponder(&mut self) -> Result<(), Box<dyn Error>> {
+        // Engines that advertise Ponder expect to be told before they are asked to ponder
+        self.is_ponder_enabled = true;
+        match self.has_option("Ponder") {
+            true => self.set_option("Ponder", "true").await,
+            false => Ok(()),
+        }
+    }
+
+    pub async fn reset(&mut self, starting_board: &GameBoard) -> Result<(), Box<dyn Error>> {
+        // Chess960 engines expect castles as king takes rook, which only UCI_Chess960 turns on
+        let is_chess960 = starting_board.is_chess960();
+        let is_chess960_applied = self.applied_options.iter().any(|(name, value)| {
+            name.eq_ignore_ascii_case(CHESS960_OPTION_NAME) && value == "true"
+        });
+        if is_chess960 != is_chess960_applied {
+            match self.has_option(CHESS960_OPTION_NAME) {
+                true => {
+                    self.set_option(CHESS960_OPTION_NAME, &is_chess960.to_string())
+                        .await?
+                }
+                false if is_chess960 => {
+                    return Err(Box::from(format!(
+                        "{} does not support Chess960, it has no {CHESS960_OPTION_NAME} option",
+                        self.config.name
+                    )));
+                }
+                false => {}
+            }
+        }
+
+        self.set_position_command.clear();
+        self.set_position_command
+            .push_str(format!("position fen {} moves", starting_board.to_fen()?).as_str());
+        self.restart_count = 0;
+
+        if let Err(error) = self.send_new_game().await {
+            self.recover_from(error).await?;
+        }
+        Ok(())
+    }
+
+    pub fn record_move_played(&mut self, chess_move: &ChessMove) {
+        self.set_position_command
+            .push_str(format!(" {}", chess_move.uci_notation()).as_str());
+    }
+
+    pub async fn start_search(
+        &mut self,
+        limits: &SearchLimits,
+        line_count: u32,
+    ) -> Result<(), Box<dyn Error>> {
+        if self.current_search.is_some() {
+            return Err(Box::new(EngineError::AlreadySearching {
+                engine: self.config.name.clone(),
+            }));
+        }
+        if line_count == 0 {
+            return Err(Box::from("A search needs at least one line"));
+        }
+
+        // Only touch MultiPV when it changes so regular searches skip the extra round trip
+        if line_count != self.multi_pv {
+            self.set_option("MultiPV", line_count.to_string().as_str())
+                .await?;
+            self.multi_pv = line_count;
+        }
+
+        self.current_search = Some(limits.clone());
+        if let Err(error) = self.send_search().await {
+            self.recover_from(error).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn analyse_multipv(
+        &mut self,
+        line_count: u32,
+        limits: &SearchLimits,
+    ) -> Result<Vec<SearchInfo>, Box<dyn Error>> {
+        // Top line_count moves ranked best first, each with its score and principal variation
+        self.start_search(limits, line_count).await?;
+        Ok(self.wait_for_search().await?.candidates)
+    }
+
+    pub async fn search_unless_pondered(
+        &mut self,
+        limits: &SearchLimits,
+        line_count: u32,
+    ) -> Result<(), Box<dyn Error>> {
+        // A ponder hit leaves the engine already searching the current position
+        match self.is_searching() {
+            true => Ok(()),
+            false => self.start_search(limits, line_count).await,
+        }
+    }
+
+    pub async fn ponder_expected_reply(
+        &mut self,
+        board: &GameBoard,
+        ponder_move: Option<&ChessMove>,
+        limits: &SearchLimits,
+        line_count: u32,
+    ) -> Result<(), Box<dyn Error>> {
+        // Show the reply the engine expects and think about it on the opponent's time
+        let expected_reply = match ponder_move {
+            Some(ponder_move) => board.find_legal_move(ponder_move)?,
+            None => None,
+        };
+        let Some(expected_reply) = expected_reply else {
+            return Ok(());
+        };
+
+        println!(
+            "EXPECTED REPLY: {} ({expected_reply})",
+            expected_reply.san_notation(board)?
+        );
+        match self.is_ponder_enabled {
+            true => self.start_ponder(limits, line_count, &expected_reply).await,
+            false => Ok(()),
+        }
+    }
+
+    async fn start_ponder(
+        &mut self,
+        limits: &SearchLimits,
+        line_count: u32,
+        ponder_move: &ChessMove,
+    ) -> Result<(), Box<dyn Error>> {
+        // Think on the opponent's time as if they already played the expected reply
+        let ponder_limits = SearchLimits {
+            ponder: true,
+            infinite: false,
+            ..limits.clone()
+        };
+        self.ponder_move = Some(ponder_move.clone());
+        self.start_search(&ponder_limits, line_count).await
+    }
+
+    pub async fn record_opponent_move(
+        &mut self,
+        chess_move: &ChessMove,
+    ) -> Result<(), Box<dyn Error>> {
+        // Keep the ponder search going when the opponent played the reply we expected
+        let is_ponder_hit = self
+            .ponder_move
+            .as_ref()
+            .is_some_and(|ponder_move| ponder_move.uci_notation() == chess_move.uci_notation());
+        if is_ponder_hit {
+            println!("PONDER HIT");
+            return self.ponder_hit().await;
+        }
+
+        self.abandon_search().await;
+        self.record_move_played(chess_move);
+        Ok(())
+    }
+
+    async fn ponder_hit(&mut self) -> Result<(), Box<dyn Error>> {
+        // The opponent played the expected reply so the ponder search becomes the real one
+        let (Some(ponder_move), Some(limits)) = (self.ponder_move.take(), &mut self.current_search)
+        else {
+            return Err(Box::from(format!("{} is not pondering", self.config.name)));
+        };
+        limits.ponder = false;
+        self.search_deadline = Self::get_search_deadline(limits);
+        self.record_move_played(&ponder_move);
+
+        if let Err(error) = self.run_engine_command("ponderhit").await {
+            self.recover_from(error).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn wait_for_search(&mut self) -> Result<SearchResult, Box<dyn Error>> {
+        loop {
+            match self.try_wait_for_search().await {
+                Ok(search_result) => {
+                    self.restart_count = 0;
+                    return Ok(search_result);
+                }
+                // A restarted engine is sent the same search again, so just keep waiting
+                Err(error) => {
+                    if let Err(error) = self.recover_from(error).await {
+                        self.clear_search();
+                        return Err(error);
+                    }
+                }
+            }
+        }
+    }
+
+    pub async fn abandon_search(&mut self) {
+        // Whatever the engine found for a position we no longer care about is thrown away,
+        // and any engine failure will show up again on the next real search
+        if self.is_searching() {
+            self.stop_search().await.ok();
+        }
+    }
+
+    pub async fn stop_search(&mut self) -> Result<SearchResult, Box<dyn Error>> {
+        self.is_stopping = true;
+        if let Err(error) = self.run_engine_command("stop").await {
+            self.recover_from(error).await?;
+        }
+        self.wait_for_search().await
+    }
+
+    async fn restart(&mut self) -> Result<(), Box<dyn Error>> {
+        // The old process may already be gone, in which case there is nothing left to kill
+        let _ = self.process.start_kill();
+
+        let (process, input, output) = Self::spawn_process(&self.config)?;
+        self.process = process;
+        self.input = input;
+        self.output = output;
+        self.pending_output.clear();
+        self.options.clear();
+
+        self.initialize_uci().await?;
+        for (name, value) in self.applied_options.clone() {
+            self.send_option(&name, &value).await?;
+        }
+        self.send_new_game().await?;
+
+        if self.current_search.is_some() {
+            self.send_search().await?;
+            if self.is_stopping {
+                self.run_engine_command("stop").await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn spawn_process(
+        config: &EngineConfig,
+    ) -> Result<(Child, ChildStdin, UnboundedReceiver<String>), Box<dyn Error>> {
+        let mut process = Command::new(&config.path)
+            .args(&config.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .context(format!("Failed to launch engine {config}"))?;
+
+        if let Some(status_code) = process
+            .try_wait()
+            .context(format!("Failed to check status of engine {}", config.name))?
+        {
+            return Err(Box::new(EngineError::Crashed {
+                engine: config.name.clone(),
+                status: Some(status_code),
+            }));
+        }
+
+        let input = process
+            .stdin
+            .take()
+            .context(format!("Failed to open {} stdin", config.name))?;
+        let stdout = process
+            .stdout
+            .take()
+            .context(format!("Failed to open {} stdout", config.name))?;
+
+        // Read engine output in the background so a long search never blocks the runtime
+        let (output_sender, output) = unbounded_channel();
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stdout).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                if output_sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok((process, input, output))
+    }
+
+    async fn recover_from(&mut self, error: Box<dyn Error>) -> Result<(), Box<dyn Error>> {
+        let mut error = error;
+
+        loop {
+            let is_recoverable = error
+                .downcast_ref::<EngineError>()
+                .is_some_and(|error| error.is_recoverable_by_restart());
+            if !is_recoverable || self.restart_count >= MAX_RESTART_ATTEMPTS {
+                return Err(error);
+            }
+
+            self.restart_count += 1;
+            println!(
+                "{error}, restarting it (attempt {}/{MAX_RESTART_ATTEMPTS})",
+                self.restart_count
+            );
+            match self.restart().await {
+                Ok(()) => return Ok(()),
+                Err(restart_error) => error = restart_error,
+            }
+        }
+    }
+
+    async fn try_wait_for_search(&mut self) -> Result<SearchResult, Box<dyn Error>> {
+        if self.current_search.is_none() {
+            return Err(Box::new(EngineError::NotSearching {
+                engine: self.config.name.clone(),
+            }));
+        }
+
+        let output_lines = match self
+            .query_engine_output("bestmove", self.search_deadline)
+            .await
+        {
+            // Cut an overdue search short and take whatever the engine has found so far
+            Err(error) if matches!(error.downcast_ref(), Some(EngineError::Timeout { .. })) => {
+                println!("{error}, stopping the search");
+                self.run_engine_command("stop").await?;
+                self.query_engine_output("bestmove", Some(Instant::now() + STOP_GRACE))
+                    .await?
+            }
+            output_lines => output_lines?,
+        };
+
+        self.clear_search();
+        SearchResult::from_search_output(&output_lines)
+    }
+
+    fn clear_search(&mut self) {
+        self.current_search = None;
+        self.ponder_move = None;
+        self.search_deadline = None;
+        self.is_stopping = false;
+    }
+
+    async fn initialize_uci(&mut self) -> Result<(), Box<dyn Error>> {
+        self.run_engine_command("uci").await?;
+
+        for line in self
+            .query_engine_output("uciok", Some(Instant::now() + READY_TIMEOUT))
+            .await?
+        {
+            if let Some(name) = line.strip_prefix("id name ") {
+                self.name = name.to_string();
+            } else if let Some(author) = line.strip_prefix("id author ") {
+                self.author = author.to_string();
+            } else if line.starts_with("option ") {
+                self.options.push(UciOption::from_option_line(&line)?);
+            }
+        }
+
+        self.wait_until_ready().await
+    }
+
+    async fn send_option(&mut self, name: &str, value: &str) -> Result<(), Box<dyn Error>> {
+        let command = match self.options.iter().find(|option| option.name == name) {
+            Some(UciOption {
+                option_type: UciOptionType::Button,
+                ..
+            }) => format!("setoption name {name}"),
+            _ => format!("setoption name {name} value {value}"),
+        };
+        self.run_engine_command(command.as_str()).await?;
+        self.wait_until_ready().await
+    }
+
+    async fn send_new_game(&mut self) -> Result<(), Box<dyn Error>> {
+        self.run_engine_command("ucinewgame").await?;
+        self.run_engine_command(self.set_position_command.clone().as_str())
+            .await?;
+        self.wait_until_ready().await
+    }
+
+    async fn send_search(&mut self) -> Result<(), Box<dyn Error>> {
+        let limits = self
+            .current_search
+            .clone()
+            .context("Cannot send a search without search limits")?;
+
+        self.search_deadline = Self::get_search_deadline(&limits);
+
+        let mut position_command = self.set_position_command.clone();
+        if let (true, Some(ponder_move)) = (limits.ponder, &self.ponder_move) {
+            position_command.push_str(format!(" {}", ponder_move.uci_notation()).as_str());
+        }
+        self.run_engine_command(position_command.as_str()).await?;
+        self.run_engine_command(limits.go_command().as_str()).await
+    }
+
+    fn get_search_deadline(limits: &SearchLimits) -> Option<Instant> {
+        // Infinite and ponder searches only end when told to, anything else gets its budget plus some slack
+        match limits.infinite || limits.ponder {
+            true => None,
+            false => Some(
+                Instant::now()
+                    + limits
+                        .time_budget()
+                        .map_or(DEFAULT_SEARCH_TIMEOUT, |budget| {
+                            budget + SEARCH_TIMEOUT_GRACE
+                        }),
+            ),
+        }
+    }
+
+    async fn wait_until_ready(&mut self) -> Result<(), Box<dyn Error>> {
+        self.run_engine_command("isready").await?;
+        self.query_engine_output("readyok", Some(Instant::now() + READY_TIMEOUT))
+            .await?;
+        Ok(())
+    }
+
+    async fn run_engine_command(&mut self, command: &str) -> Result<(), Box<dyn Error>> {
+        // A write only fails once the engine has gone away and closed its end of the pipe
+        let write_result = match self
+            .input
+            .write_all(format!("{command}\n").as_bytes())
+            .await
+        {
+            Ok(()) => self.input.flush().await,
+            Err(error) => Err(error),
+        };
+        if write_result.is_err() {
+            return Err(Box::new(self.crashed_error().await));
+        }
+
+        Ok(())
+    }
+
+    async fn query_engine_output(
+        &mut self,
+        pattern: &str,
+        deadline: Option<Instant>,
+    ) -> Result<Vec<String>, Box<dyn Error>> {
+        loop {
+            let line = match deadline {
+                Some(deadline) => timeout_at(deadline, self.output.recv()).await,
+                None => Ok(self.output.recv().await),
+            };
+
+            match line {
+                Ok(Some(line)) => {
+                    let is_match = line.starts_with(pattern);
+                    self.pending_output.push(line);
+                    if is_match {
+                        return Ok(std::mem::take(&mut self.pending_output));
+                    }
+                }
+                Ok(None) => return Err(Box::new(self.crashed_error().await)),
+                Err(_) => {
+                    return Err(Box::new(EngineError::Timeout {
+                        engine: self.config.name.clone(),
+                        waiting_for: pattern.to_string(),
+                    }));
+                }
+            }
+        }
+    }
+
+    async fn crashed_error(&mut self) -> EngineError {
+        // Give the process a moment to exit so the error can say how it died
+        let status = timeout(EXIT_STATUS_WAIT, self.process.wait())
+            .await
+            .ok()
+            .and_then(|status| status.ok());
+
+        EngineError::Crashed {
+            engine: self.config.name.clone(),
+            status,
+        }
+    }
+}
@@ -0,0 +1,249 @@
+use anyhow::Context;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+const OPTION_KEYWORDS: [&str; 5] = ["type", "default", "min", "max", "var"];
+const EMPTY_STRING_VALUE: &str = "<empty>";
+
+#[derive(Clone, PartialEq)]
+pub enum UciOptionType {
+    Check {
+        default: bool,
+    },
+    Spin {
+        default: i64,
+        min: i64,
+        max: i64,
+    },
+    Combo {
+        default: String,
+        values: Vec<String>,
+    },
+    Button,
+    String {
+        default: String,
+    },
+}
+
+#[derive(Clone, PartialEq)]
+pub struct UciOption {
+    pub name: String,
+    pub option_type: UciOptionType,
+}
+
+impl UciOption {
+    pub fn from_option_line(line: &str) -> Result<Self, Box<dyn Error>> {
+        // option name Skill Level type spin default 20 min 0 max 20
+        let mut tokens = line.split_whitespace();
+        if tokens.next() != Some("option") || tokens.next() != Some("name") {
+            return Err(Box::from(format!("Not a UCI option line: {line}")));
+        }
+
+        // Names and values can contain spaces, so each field runs until the next keyword
+        let mut name_tokens: Vec<&str> = Vec::new();
+        let mut fields: Vec<(&str, Vec<&str>)> = Vec::new();
+        for token in tokens {
+            if OPTION_KEYWORDS.contains(&token) {
+                fields.push((token, Vec::new()));
+                continue;
+            }
+            match fields.last_mut() {
+                Some((_, values)) => values.push(token),
+                None => name_tokens.push(token),
+            }
+        }
+
+        let get_field = |keyword: &str| -> Option<String> {
+            fields
+                .iter()
+                .find(|(field_keyword, _)| *field_keyword == keyword)
+                .map(|(_, values)| values.join(" "))
+        };
+        let get_number_field = |keyword: &str| -> Result<i64, Box<dyn Error>> {
+            let value = get_field(keyword)
+                .context(format!("Missing {keyword} in UCI option line: {line}"))?;
+            value
+                .parse::<i64>()
+                .map_err(|_| Box::from(format!("Invalid {keyword} '{value}' in: {line}")))
+        };
+
+        let option_type = match get_field("type")
+            .context(format!("Missing type in UCI option line: {line}"))?
+            .as_str()
+        {
+            "check" => UciOptionType::Check {
+                default: get_field("default").is_some_and(|value| value == "true"),
+            },
+            "spin" => UciOptionType::Spin {
+                default: get_number_field("default")?,
+                min: get_number_field("min")?,
+                max: get_number_field("max")?,
+            },
+            "combo" => UciOptionType::Combo {
+                default: get_field("default").unwrap_or_default(),
+                values: fields
+                    .iter()
+                    .filter(|(keyword, _)| *keyword == "var")
+                    .map(|(_, values)| values.join(" "))
+                    .collect(),
+            },
+            "button" => UciOptionType::Button,
+            "string" => UciOptionType::String {
+                default: get_field("default")
+                    .filter(|value| value != EMPTY_STRING_VALUE)
+                    .unwrap_or_default(),
+            },
+            bad_type => {
+                return Err(Box::from(format!(
+                    "Found unknown option type '{bad_type}' in: {line}"
+                )));
+            }
+        };
+
+        Ok(Self {
+            name: name_tokens.join(" "),
+            option_type,
+        })
+    }
+
+    pub fn validate_value(&self, value: &str) -> Result<(), Box<dyn Error>> {
+        match &self.option_type {
+            UciOptionType::Check { .. } if value != "true" && value != "false" => Err(Box::from(
+                format!("Option {} expects true or false, found: {value}", self.name),
+            )),
+            UciOptionType::Spin { min, max, .. } => {
+                let number = value.parse::<i64>().map_err(|_| {
+                    format!("Option {} expects a number, found: {value}", self.name)
+                })?;
+                match (*min..=*max).contains(&number) {
+                    true => Ok(()),
+                    false => Err(Box::from(format!(
+                        "Option {} must be between {min} and {max}, found: {number}",
+                        self.name
+                    ))),
+                }
+            }
+            // Combo values are case insensitive according to the UCI spec
+            UciOptionType::Combo { values, .. }
+                if !values.iter().any(|item| item.eq_ignore_ascii_case(value)) =>
+            {
+                Err(Box::from(format!(
+                    "Option {} expects one of [{}], found: {value}",
+                    self.name,
+                    values.join(", ")
+                )))
+            }
+            UciOptionType::Button if !value.is_empty() => Err(Box::from(format!(
+                "Option {} is a button and does not take a value",
+                self.name
+            ))),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Display for UciOption {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let details = match &self.option_type {
+            UciOptionType::Check { default } => format!("check, default {default}"),
+            UciOptionType::Spin { default, min, max } => {
+                format!("spin, default {default}, min {min}, max {max}")
+            }
+            UciOptionType::Combo { default, values } => {
+                format!("combo, default {default}, values [{}]", values.join(", "))
+            }
+            UciOptionType::Button => String::from("button"),
+            UciOptionType::String { default } => format!("string, default \"{default}\""),
+        };
+        write!(f, "{} ({})", self.name, details)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn option_lines_read_every_option_type() {
+        let option = UciOption::from_option_line(
+            "option name Skill Level type spin default 20 min 0 max 20",
+        )
+        .unwrap();
+        assert_eq!(option.name, "Skill Level");
+        assert!(
+            option.option_type
+                == UciOptionType::Spin {
+                    default: 20,
+                    min: 0,
+                    max: 20
+                }
+        );
+
+        let option =
+            UciOption::from_option_line("option name UCI_Chess960 type check default false")
+                .unwrap();
+        assert!(option.option_type == UciOptionType::Check { default: false });
+
+        let option = UciOption::from_option_line(
+            "option name Analysis Contempt type combo default Both var Off var White var Black var Both",
+        )
+        .unwrap();
+        assert!(
+            option.option_type
+                == UciOptionType::Combo {
+                    default: String::from("Both"),
+                    values: ["Off", "White", "Black", "Both"].map(String::from).to_vec(),
+                }
+        );
+
+        let option = UciOption::from_option_line("option name Clear Hash type button").unwrap();
+        assert_eq!(option.name, "Clear Hash");
+        assert!(option.option_type == UciOptionType::Button);
+
+        // Strings keep their spaces, and "<empty>" stands for no default at all
+        let option = UciOption::from_option_line(
+            "option name EvalFile type string default nn-1111 cafe.nnue",
+        )
+        .unwrap();
+        assert!(
+            option.option_type
+                == UciOptionType::String {
+                    default: String::from("nn-1111 cafe.nnue")
+                }
+        );
+        let option =
+            UciOption::from_option_line("option name SyzygyPath type string default <empty>")
+                .unwrap();
+        assert!(
+            option.option_type
+                == UciOptionType::String {
+                    default: String::new()
+                }
+        );
+    }
+
+    #[test]
+    fn bad_option_lines_are_errors() {
+        assert!(UciOption::from_option_line("id name Stockfish").is_err());
+        assert!(UciOption::from_option_line("option name Hash").is_err());
+        assert!(UciOption::from_option_line("option name Hash type spin default 16").is_err());
+        assert!(UciOption::from_option_line("option name Hash type slider default 16").is_err());
+    }
+
+    #[test]
+    fn values_are_checked_against_the_option() {
+        let option =
+            UciOption::from_option_line("option name Threads type spin default 1 min 1 max 512")
+                .unwrap();
+        assert!(option.validate_value("4").is_ok());
+        assert!(option.validate_value("0").is_err());
+        assert!(option.validate_value("four").is_err());
+
+        let option = UciOption::from_option_line(
+            "option name Style type combo default Normal var Solid var Normal",
+        )
+        .unwrap();
+        assert!(option.validate_value("solid").is_ok());
+        assert!(option.validate_value("Risky").is_err());
+    }
+}